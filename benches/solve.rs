@@ -0,0 +1,59 @@
+//! Solves a fixed corpus of generated deals with each search mode and reports how many states
+//! the search expands per second.
+//!
+//! Run with `cargo bench`. The corpus and the budgets never change, so the numbers of two builds
+//! can be compared directly.
+
+use sissu::{generate, solve_with, Budget, SearchMode, SolveError, SolveOptions};
+
+use std::time::Duration;
+
+/// Seeds of the dealt corpus.
+const SEEDS: std::ops::Range<u64> = 0..100;
+/// Most states a single deal may expand, so that hard deals do not dominate the run.
+const MAX_EXPANDED: usize = 50_000;
+
+fn main() {
+    for mode in [
+        SearchMode::BestFirst,
+        SearchMode::AStar,
+        SearchMode::IdaStar,
+    ] {
+        let options = SolveOptions {
+            mode,
+            budget: Budget {
+                max_expanded: Some(MAX_EXPANDED),
+                ..Budget::default()
+            },
+            ..SolveOptions::default()
+        };
+
+        let mut expanded = 0;
+        let mut elapsed = Duration::ZERO;
+        let mut solved = 0;
+        for seed in SEEDS {
+            let stats = match solve_with(&generate(seed), &options) {
+                Ok(solution) => {
+                    solved += 1;
+                    solution.stats
+                }
+                Err(
+                    SolveError::Exhausted(stats)
+                    | SolveError::Unsolvable { stats, .. }
+                    | SolveError::BudgetExceeded { stats, .. },
+                ) => stats,
+                Err(error) => panic!("seed {seed}: {error}"),
+            };
+            expanded += stats.expanded;
+            elapsed += stats.elapsed;
+        }
+
+        println!(
+            "{:<11} {solved:>3}/{} solved, {expanded:>9} states in {:>7.3}s, {:>9.0} states/s",
+            format!("{mode:?}"),
+            SEEDS.end - SEEDS.start,
+            elapsed.as_secs_f64(),
+            expanded as f64 / elapsed.as_secs_f64(),
+        );
+    }
+}
@@ -1,73 +1,1042 @@
-use crate::io::print_solution;
-use crate::rule::{Action, Card, SLOT_COUNT, TRAY_COUNT};
-use crate::state::State;
+use crate::heuristic::{Classic, Heuristic};
+use crate::rule::{validate_game, Action, AutoMove};
+use crate::state::{Board, Packed, State};
 
 use std::{
-    cmp::Reverse,
-    collections::{BinaryHeap, HashSet},
-    rc::Rc,
+    cmp::Ordering,
+    collections::{hash_map::Entry, BinaryHeap, HashMap, HashSet},
+    hash::{BuildHasher, RandomState},
+    str::FromStr,
+    sync::{
+        atomic::{self, AtomicBool, AtomicUsize},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
-pub(crate) fn solve(trays: &[Vec<Card>; TRAY_COUNT], slots: &[Option<Card>; SLOT_COUNT]) {
-    if trays.iter().all(|tray| tray.is_empty()) {
-        return;
+/// Counters describing how much work a search did.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Stats {
+    /// Number of states popped from the queue and expanded.
+    pub expanded: usize,
+    /// Largest number of states held in memory at once.
+    pub stored: usize,
+    pub elapsed: Duration,
+}
+
+/// An action of a solution, followed by the moves the game then makes by itself.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Step {
+    pub action: Action,
+    pub auto_moves: Vec<AutoMove>,
+}
+
+/// A sequence of actions that clears the board, as found by [`solve`].
+#[derive(Clone, Debug)]
+pub struct Solution {
+    /// Moves the game makes by itself before the first action.
+    pub initial_auto_moves: Vec<AutoMove>,
+    pub steps: Vec<Step>,
+    pub stats: Stats,
+}
+
+impl Solution {
+    pub fn actions(&self) -> impl Iterator<Item = Action> + '_ {
+        self.steps.iter().map(|step| step.action)
+    }
+
+    /// Replays the solution from `deal`, the board it was found for, and returns the board after
+    /// each step.
+    pub fn boards(&self, deal: &Board) -> Vec<Board> {
+        let mut state = Arc::new(State::with_board(deal));
+        self.steps
+            .iter()
+            .map(|step| {
+                state = state.transit(&step.action);
+                state.to_board()
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum SolveError {
+    /// The board failed [`validate_game`], or the options cannot be used.
+    InvalidInput(String),
+    /// Every state the search generated was expanded without clearing the board, by a search
+    /// that does not count the positions it examined. After [`Successors::Pruned`], which skips
+    /// some moves, the board may still be solvable. After [`SearchMode::IdaStar`], which takes
+    /// every legal action, the board has no solution.
+    Exhausted(Stats),
+    /// A search over every legal action examined every reachable position, none of them a
+    /// cleared board: the board has no solution.
+    Unsolvable { positions: usize, stats: Stats },
+    /// The search gave up on reaching a limit of its [`Budget`].
+    BudgetExceeded { limit: Limit, stats: Stats },
+}
+
+/// The limit of a [`Budget`] that stopped a search.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Limit {
+    Expanded,
+    Time,
+    Stored,
+}
+
+/// Upper bounds on the work a search may do before giving up. `None` means unlimited.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Budget {
+    pub max_expanded: Option<usize>,
+    pub max_time: Option<Duration>,
+    pub max_stored: Option<usize>,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum SearchMode {
+    /// Greedy best-first search. Usually fast, but the solution can be far from the shortest.
+    #[default]
+    BestFirst,
+    /// A* search on the number of actions. The solution is guaranteed to be the shortest.
+    AStar,
+    /// Iterative-deepening A*. Finds a shortest solution like [`SearchMode::AStar`] while only
+    /// keeping the current path (and an optional transposition table) in memory.
+    IdaStar,
+    /// Best-first search over every legal action, which either finds a solution or proves that
+    /// there is none.
+    Prove,
+}
+
+impl FromStr for SearchMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "best-first" => Ok(SearchMode::BestFirst),
+            "astar" => Ok(SearchMode::AStar),
+            "idastar" => Ok(SearchMode::IdaStar),
+            "prove" => Ok(SearchMode::Prove),
+            _ => Err(format!(
+                "Invalid search mode: {s}. Expected 'best-first', 'astar', 'idastar' or 'prove'"
+            )),
+        }
+    }
+}
+
+/// Which actions [`SearchMode::BestFirst`] takes from a state.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Successors {
+    /// Every legal action. Moves from a tray into a slot are tried after the others that look as
+    /// promising, so that the search finds a solution whenever there is one.
+    #[default]
+    All,
+    /// Moves from a tray into a slot only when no other action leads to a new state. Faster, but
+    /// the search can miss every solution of a solvable deal.
+    Pruned,
+}
+
+impl FromStr for Successors {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Successors::All),
+            "pruned" => Ok(Successors::Pruned),
+            _ => Err(format!(
+                "Invalid successors: {s}. Expected 'all' or 'pruned'"
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SolveOptions {
+    pub mode: SearchMode,
+    /// Actions taken by [`SearchMode::BestFirst`]. The other modes always take every action.
+    pub successors: Successors,
+    /// Which position [`SearchMode::BestFirst`] and [`SearchMode::Prove`] expand next. A* and
+    /// IDA* always rely on [`State::lower_bound`], since a shortest solution depends on it.
+    pub heuristic: Arc<dyn Heuristic>,
+    /// Maximum number of states remembered between branches by [`SearchMode::IdaStar`].
+    /// Zero disables the transposition table.
+    pub transposition_table_size: usize,
+    /// Threads searching together. [`SearchMode::BestFirst`] and [`SearchMode::Prove`] split
+    /// the positions between them; the other modes always search on a single thread.
+    pub workers: usize,
+    pub budget: Budget,
+    /// Called with the running counters every 10000 expanded states.
+    pub progress: Option<fn(&Stats)>,
+}
+
+impl Default for SolveOptions {
+    fn default() -> Self {
+        Self {
+            mode: SearchMode::default(),
+            successors: Successors::default(),
+            heuristic: Arc::new(Classic),
+            transposition_table_size: 1 << 16,
+            workers: 1,
+            budget: Budget::default(),
+            progress: None,
+        }
+    }
+}
+
+/// Keeps the counters of a running search and checks them against its budget.
+struct Meter<'a> {
+    options: &'a SolveOptions,
+    start_time: Instant,
+    expanded: usize,
+    stored: usize,
+    /// Expanded states of every worker of a parallel search, which the budget applies to.
+    shared_expanded: Option<&'a AtomicUsize>,
+}
+
+impl<'a> Meter<'a> {
+    fn new(options: &'a SolveOptions) -> Self {
+        Self {
+            options,
+            start_time: Instant::now(),
+            expanded: 0,
+            stored: 0,
+            shared_expanded: None,
+        }
+    }
+
+    fn stats(&self) -> Stats {
+        Stats {
+            expanded: self.expanded,
+            stored: self.stored,
+            elapsed: self.start_time.elapsed(),
+        }
+    }
+
+    /// Counts one more expanded state while `stored` states are held in memory.
+    fn expand(&mut self, stored: usize) -> Result<(), Limit> {
+        self.expanded = match self.shared_expanded {
+            Some(expanded) => expanded.fetch_add(1, atomic::Ordering::Relaxed) + 1,
+            None => self.expanded + 1,
+        };
+        self.stored = self.stored.max(stored);
+
+        if let Some(progress) = self.options.progress {
+            if self.expanded.is_multiple_of(10000) {
+                progress(&self.stats());
+            }
+        }
+
+        let budget = &self.options.budget;
+        if budget.max_expanded.is_some_and(|max| self.expanded >= max) {
+            return Err(Limit::Expanded);
+        }
+        if budget.max_stored.is_some_and(|max| self.stored >= max) {
+            return Err(Limit::Stored);
+        }
+        if budget
+            .max_time
+            .is_some_and(|max| self.start_time.elapsed() >= max)
+        {
+            return Err(Limit::Time);
+        }
+
+        Ok(())
+    }
+}
+
+pub fn solve(board: &Board) -> Result<Solution, SolveError> {
+    solve_with(board, &SolveOptions::default())
+}
+
+pub fn solve_with(board: &Board, options: &SolveOptions) -> Result<Solution, SolveError> {
+    validate_game(board).map_err(SolveError::InvalidInput)?;
+
+    let mut meter = Meter::new(options);
+
+    if board.trays.iter().all(|tray| tray.is_empty()) {
+        return Ok(Solution {
+            initial_auto_moves: Vec::new(),
+            steps: Vec::new(),
+            stats: meter.stats(),
+        });
     }
 
+    let initial_state = Arc::new(State::with_board(board));
+    let goal = match options.mode {
+        SearchMode::BestFirst | SearchMode::Prove if options.workers > 1 => {
+            let successors = match options.mode {
+                SearchMode::Prove => Successors::All,
+                _ => options.successors,
+            };
+            let heuristic = &*options.heuristic;
+            ParallelSearch::run(
+                options.workers,
+                successors,
+                heuristic,
+                initial_state,
+                &mut meter,
+            )
+        }
+        SearchMode::BestFirst => best_first(
+            initial_state,
+            options.successors,
+            &*options.heuristic,
+            &mut meter,
+        ),
+        SearchMode::Prove => best_first(
+            initial_state,
+            Successors::All,
+            &*options.heuristic,
+            &mut meter,
+        ),
+        SearchMode::AStar => a_star(initial_state, &mut meter),
+        SearchMode::IdaStar => IdaStar::new(options.transposition_table_size)
+            .map_err(SolveError::InvalidInput)?
+            .run(initial_state, &mut meter),
+    };
+
+    match goal {
+        Ok(Some(goal)) => {
+            let (initial_auto_moves, steps) = get_solution(&goal);
+            Ok(Solution {
+                initial_auto_moves,
+                steps,
+                stats: meter.stats(),
+            })
+        }
+        Ok(None) => match (options.mode, options.successors) {
+            // These take every legal action and remember every position they generate.
+            (SearchMode::BestFirst, Successors::All)
+            | (SearchMode::AStar | SearchMode::Prove, _) => {
+                let stats = meter.stats();
+                Err(SolveError::Unsolvable {
+                    positions: stats.stored,
+                    stats,
+                })
+            }
+            // Pruned best-first search skips moves, and IDA* forgets the positions it examined.
+            (SearchMode::BestFirst, Successors::Pruned) | (SearchMode::IdaStar, _) => {
+                Err(SolveError::Exhausted(meter.stats()))
+            }
+        },
+        Err(limit) => Err(SolveError::BudgetExceeded {
+            limit,
+            stats: meter.stats(),
+        }),
+    }
+}
+
+/// How much worse than its estimate a state reached by a move into a slot is considered by
+/// [`best_first`], since such moves are rarely needed but many of them are always possible.
+const SLOT_MOVE_PENALTY: f64 = 3.;
+
+/// A position generated by a search, packed until it is expanded.
+struct Record {
+    packed: Packed,
+    step: usize,
+    /// The record of the position this one was reached from, and the action taken there.
+    parent: Option<(usize, Action)>,
+}
+
+/// Every position a search generated, which the open list refers to by index.
+#[derive(Default)]
+struct Arena(Vec<Record>);
+
+impl Arena {
+    /// Records `state`, reached from the record `parent` if it is not the initial state, and
+    /// returns its index.
+    fn push(&mut self, state: &State, parent: Option<usize>) -> usize {
+        self.0.push(Record {
+            packed: Packed::new(state),
+            step: state.step,
+            parent: parent.zip(state.action),
+        });
+        self.0.len() - 1
+    }
+
+    fn unpack(&self, index: usize) -> Arc<State> {
+        let record = &self.0[index];
+        Arc::new(record.packed.unpack(record.step))
+    }
+
+    /// Takes the actions leading to the record `index` from `initial_state` again, so that the
+    /// state returned links back to it through every step.
+    fn replay(&self, index: usize, initial_state: &Arc<State>) -> Arc<State> {
+        let mut actions = Vec::new();
+        let mut record = &self.0[index];
+        while let Some((parent, action)) = record.parent {
+            actions.push(action);
+            record = &self.0[parent];
+        }
+        replay(initial_state, actions.iter().rev())
+    }
+}
+
+/// Takes `actions` from `initial_state`, so that the state returned links back to it through
+/// every step.
+fn replay<'a>(initial_state: &Arc<State>, actions: impl Iterator<Item = &'a Action>) -> Arc<State> {
+    let mut state = initial_state.clone();
+    for action in actions {
+        state = state.transit(action);
+    }
+    state
+}
+
+/// An entry of an open list, ordered so that the max-heap pops the lowest priority first.
+struct MinPriority<T> {
+    priority: f64,
+    item: T,
+}
+
+impl<T> PartialEq for MinPriority<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for MinPriority<T> {}
+
+impl<T> PartialOrd for MinPriority<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for MinPriority<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.total_cmp(&self.priority)
+    }
+}
+
+/// Expands the state with the lowest estimate first, taking the actions `successors` allows.
+fn best_first<H: Heuristic + ?Sized>(
+    initial_state: Arc<State>,
+    successors: Successors,
+    heuristic: &H,
+    meter: &mut Meter,
+) -> Result<Option<Arc<State>>, Limit> {
+    let mut arena = Arena::default();
     let mut heap = BinaryHeap::new();
     let mut visited_states = HashSet::new();
 
-    let current_state = Rc::new(State::with_trays_and_slots(trays, slots));
-    heap.push(Reverse(current_state.clone()));
-    visited_states.insert(current_state);
-
-    // for action in current_state.clone().valid_actions() {
-    //     println!("[DEBUG] {action}");
-    // }
-    // for action in current_state.clone().valid_slot_actions() {
-    //     println!("[DEBUG] {action}");
-    // }
-    // unimplemented!();
+    heap.push(MinPriority {
+        priority: heuristic.estimate(&initial_state),
+        item: arena.push(&initial_state, None),
+    });
+    visited_states.insert(Packed::canonical(&initial_state));
 
-    let mut iteration_count = 0_usize; // aux
-    while let Some(Reverse(current_state)) = heap.pop() {
+    while let Some(MinPriority { item: index, .. }) = heap.pop() {
+        let current_state = arena.unpack(index);
         if current_state.card_count == 0 {
-            print_solution(&get_solution(&current_state), iteration_count);
-            return;
+            return Ok(Some(arena.replay(index, &initial_state)));
+        }
+
+        let mut state_transit_by_actions =
+            |state: &Arc<State>, actions: &[Action], penalty: f64| -> usize {
+                let mut valid_actions = 0;
+                for action in actions {
+                    let new_state = state.transit(action);
+                    if visited_states.insert(Packed::canonical(&new_state)) {
+                        heap.push(MinPriority {
+                            priority: heuristic.estimate(&new_state) + penalty,
+                            item: arena.push(&new_state, Some(index)),
+                        });
+                        valid_actions += 1;
+                    }
+                }
+                valid_actions
+            };
+
+        let new_states =
+            state_transit_by_actions(&current_state, &current_state.valid_actions(), 0.);
+        match successors {
+            Successors::All => {
+                let actions = current_state.valid_slot_actions();
+                state_transit_by_actions(&current_state, &actions, SLOT_MOVE_PENALTY);
+            }
+            Successors::Pruned if new_states == 0 => {
+                let actions = current_state.valid_slot_actions();
+                state_transit_by_actions(&current_state, &actions, 0.);
+            }
+            Successors::Pruned => {}
+        }
+
+        meter.expand(visited_states.len())?;
+    }
+
+    Ok(None)
+}
+
+/// Shards of the visited set of a [`ParallelSearch`], each locked on its own.
+const SHARD_COUNT: usize = 64;
+
+/// A shard of the visited set of a [`ParallelSearch`]: each position mapped to the one it was
+/// first reached from and the action taken there, if any.
+type Shard = Mutex<HashMap<Packed, Option<(Packed, Action)>>>;
+
+/// A position waiting in the open list of a worker of a [`ParallelSearch`].
+struct Job {
+    packed: Packed,
+    step: usize,
+}
+
+/// Best-first search on several threads, each owning the positions that hash to it.
+///
+/// A worker expands the most promising position it owns and sends every new successor to the
+/// worker owning it. The visited set is shared, and maps each position to the one it was first
+/// reached from, packed with its real layout, and the action taken there.
+struct ParallelSearch<'a, H: Heuristic + ?Sized> {
+    successors: Successors,
+    heuristic: &'a H,
+    shards: Vec<Shard>,
+    hasher: RandomState,
+    visited: AtomicUsize,
+    senders: Vec<Sender<MinPriority<Job>>>,
+    /// Positions sent to a worker and not fully expanded yet. The search space is exhausted
+    /// once it drops to zero.
+    pending: AtomicUsize,
+    /// Set once a worker found a solution or reached a limit of the budget.
+    stop: AtomicBool,
+}
+
+impl<'a, H: Heuristic + ?Sized> ParallelSearch<'a, H> {
+    fn new(
+        workers: usize,
+        successors: Successors,
+        heuristic: &'a H,
+    ) -> (Self, Vec<Receiver<MinPriority<Job>>>) {
+        let (senders, receivers) = (0..workers).map(|_| mpsc::channel()).unzip();
+        let search = Self {
+            successors,
+            heuristic,
+            shards: (0..SHARD_COUNT).map(|_| Mutex::default()).collect(),
+            hasher: RandomState::new(),
+            visited: AtomicUsize::new(0),
+            senders,
+            pending: AtomicUsize::new(0),
+            stop: AtomicBool::new(false),
+        };
+        (search, receivers)
+    }
+
+    /// Records `state`, reached by `parent` unless it is the initial state, and sends it to its
+    /// worker. Returns whether the position was new.
+    fn visit(&self, state: &State, parent: Option<(Packed, Action)>, penalty: f64) -> bool {
+        let key = Packed::canonical(state);
+        let hash = self.hasher.hash_one(key) as usize;
+        match self.shards[hash % SHARD_COUNT].lock().unwrap().entry(key) {
+            Entry::Occupied(_) => return false,
+            Entry::Vacant(entry) => {
+                entry.insert(parent);
+            }
         }
+        self.visited.fetch_add(1, atomic::Ordering::Relaxed);
+
+        self.pending.fetch_add(1, atomic::Ordering::SeqCst);
+        let worker = hash / SHARD_COUNT % self.senders.len();
+        // Workers keep their receiver until the search is over, so sending cannot fail.
+        let _ = self.senders[worker].send(MinPriority {
+            priority: self.heuristic.estimate(state) + penalty,
+            item: Job {
+                packed: Packed::new(state),
+                step: state.step,
+            },
+        });
+        true
+    }
 
-        let mut state_transit_by_actions = |state: &Rc<State>, actions: &[Action]| -> usize {
-            let mut valid_actions = 0;
-            for action in actions {
-                let new_state = state.transit(action);
-                if visited_states.insert(new_state.clone()) {
-                    heap.push(Reverse(new_state));
-                    valid_actions += 1;
+    /// The position `key` was first reached from, and the action taken there.
+    fn parent(&self, key: &Packed) -> Option<(Packed, Action)> {
+        let hash = self.hasher.hash_one(key) as usize;
+        self.shards[hash % SHARD_COUNT].lock().unwrap()[key]
+    }
+
+    /// Searches from `initial_state` on `workers` threads.
+    fn run(
+        workers: usize,
+        successors: Successors,
+        heuristic: &'a H,
+        initial_state: Arc<State>,
+        meter: &mut Meter,
+    ) -> Result<Option<Arc<State>>, Limit> {
+        let (search, receivers) = Self::new(workers, successors, heuristic);
+        let expanded = AtomicUsize::new(0);
+        search.visit(&initial_state, None, 0.);
+
+        let results: Vec<_> = thread::scope(|scope| {
+            let workers: Vec<_> = receivers
+                .into_iter()
+                .map(|receiver| {
+                    let mut meter = Meter {
+                        shared_expanded: Some(&expanded),
+                        ..Meter::new(meter.options)
+                    };
+                    let search = &search;
+                    scope.spawn(move || search.work(receiver, &mut meter))
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect()
+        });
+
+        meter.expanded = expanded.into_inner();
+        meter.stored = search.visited.load(atomic::Ordering::Relaxed);
+
+        let mut outcome = Ok(None);
+        for result in results {
+            match result {
+                Ok(Some(goal)) => {
+                    outcome = Ok(Some(goal));
+                    break;
                 }
+                Err(limit) => outcome = Err(limit),
+                Ok(None) => {}
             }
-            valid_actions
+        }
+        let Some(goal) = outcome? else {
+            return Ok(None);
         };
 
-        if state_transit_by_actions(&current_state, &current_state.valid_actions()) == 0 {
-            state_transit_by_actions(&current_state, &current_state.valid_slot_actions());
+        let mut actions = Vec::new();
+        let mut key = Packed::canonical(&goal);
+        while let Some((parent, action)) = search.parent(&key) {
+            actions.push(action);
+            key = Packed::canonical(&parent.unpack(0));
         }
+        Ok(Some(replay(&initial_state, actions.iter().rev())))
+    }
+
+    /// Expands the positions sent to one worker until a worker finds a solution, the budget runs
+    /// out or no position is left anywhere.
+    fn work(
+        &self,
+        receiver: Receiver<MinPriority<Job>>,
+        meter: &mut Meter,
+    ) -> Result<Option<State>, Limit> {
+        let mut heap = BinaryHeap::new();
 
-        iteration_count += 1;
-        if iteration_count % 10000 == 0 {
-            println!("Iteration {iteration_count}");
+        while !self.stop.load(atomic::Ordering::Relaxed) {
+            heap.extend(receiver.try_iter());
+            let Some(MinPriority { item: job, .. }) = heap.pop() else {
+                if self.pending.load(atomic::Ordering::SeqCst) == 0 {
+                    return Ok(None);
+                }
+                if let Ok(job) = receiver.recv_timeout(Duration::from_millis(1)) {
+                    heap.push(job);
+                }
+                continue;
+            };
+
+            let state = Arc::new(job.packed.unpack(job.step));
+            if state.card_count == 0 {
+                self.stop.store(true, atomic::Ordering::Relaxed);
+                return Ok(Some(job.packed.unpack(job.step)));
+            }
+
+            let visit_all = |actions: &[Action], penalty: f64| {
+                let mut new_states = 0;
+                for action in actions {
+                    let new_state = state.transit(action);
+                    if self.visit(&new_state, Some((job.packed, *action)), penalty) {
+                        new_states += 1;
+                    }
+                }
+                new_states
+            };
+
+            let new_states = visit_all(&state.valid_actions(), 0.);
+            match self.successors {
+                Successors::All => {
+                    visit_all(&state.valid_slot_actions(), SLOT_MOVE_PENALTY);
+                }
+                Successors::Pruned if new_states == 0 => {
+                    visit_all(&state.valid_slot_actions(), 0.);
+                }
+                Successors::Pruned => {}
+            }
+            self.pending.fetch_sub(1, atomic::Ordering::SeqCst);
+
+            if let Err(limit) = meter.expand(self.visited.load(atomic::Ordering::Relaxed)) {
+                self.stop.store(true, atomic::Ordering::Relaxed);
+                return Err(limit);
+            }
         }
+
+        Ok(None)
     }
+}
 
-    println!("No solution found");
+/// An entry of the A* open list, ordered so that the max-heap pops the lowest `f` first and
+/// prefers deeper states among equals.
+struct Node {
+    f: usize,
+    step: usize,
+    index: usize,
 }
 
-fn get_solution(state: &Rc<State>) -> Vec<Action> {
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Node {}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.f.cmp(&self.f).then(self.step.cmp(&other.step))
+    }
+}
+
+fn a_star(initial_state: Arc<State>, meter: &mut Meter) -> Result<Option<Arc<State>>, Limit> {
+    let mut arena = Arena::default();
+    let mut heap = BinaryHeap::new();
+    // Best known step count per state. The lower bound is admissible but not necessarily
+    // consistent, so a state reached again by a shorter path is reopened.
+    let mut best_steps = HashMap::new();
+
+    best_steps.insert(Packed::canonical(&initial_state), initial_state.step);
+    heap.push(Node {
+        f: initial_state.lower_bound(),
+        step: initial_state.step,
+        index: arena.push(&initial_state, None),
+    });
+
+    while let Some(Node { step, index, .. }) = heap.pop() {
+        let state = arena.unpack(index);
+        if best_steps[&Packed::canonical(&state)] < step {
+            continue;
+        }
+        if state.card_count == 0 {
+            return Ok(Some(arena.replay(index, &initial_state)));
+        }
+
+        let actions = state.valid_actions().into_iter();
+        for action in actions.chain(state.valid_slot_actions()) {
+            let new_state = state.transit(&action);
+            let key = Packed::canonical(&new_state);
+            if best_steps
+                .get(&key)
+                .is_some_and(|&steps| steps <= new_state.step)
+            {
+                continue;
+            }
+            best_steps.insert(key, new_state.step);
+            heap.push(Node {
+                f: new_state.step + new_state.lower_bound(),
+                step: new_state.step,
+                index: arena.push(&new_state, Some(index)),
+            });
+        }
+
+        meter.expand(best_steps.len())?;
+    }
+
+    Ok(None)
+}
+
+enum Probe {
+    Found(Arc<State>),
+    /// The smallest `f` seen beyond the current bound, if any.
+    Exceeded(Option<usize>),
+}
+
+struct IdaStar {
+    /// States on the current path, to avoid walking in circles.
+    path: HashSet<Packed>,
+    /// Direct-mapped table of the smallest step count a state was reached with under the current
+    /// bound. Colliding states simply replace each other.
+    table: Vec<Option<(Packed, usize)>>,
+    table_occupied: usize,
+    hasher: RandomState,
+}
+
+impl IdaStar {
+    fn new(table_size: usize) -> Result<Self, String> {
+        let mut table = Vec::new();
+        table
+            .try_reserve_exact(table_size)
+            .map_err(|_| format!("Cannot allocate a transposition table of {table_size} states"))?;
+        table.resize(table_size, None);
+        Ok(Self {
+            path: HashSet::new(),
+            table,
+            table_occupied: 0,
+            hasher: RandomState::new(),
+        })
+    }
+
+    /// Returns whether the state packed as `key` was already searched with at most `step` steps
+    /// under the current bound, recording it otherwise.
+    fn seen(&mut self, key: &Packed, step: usize) -> bool {
+        if self.table.is_empty() {
+            return false;
+        }
+
+        let index = self.hasher.hash_one(key) as usize % self.table.len();
+        match &mut self.table[index] {
+            Some((seen_key, steps)) if seen_key == key && *steps <= step => true,
+            entry => {
+                if entry.is_none() {
+                    self.table_occupied += 1;
+                }
+                *entry = Some((*key, step));
+                false
+            }
+        }
+    }
+
+    fn run(
+        mut self,
+        initial_state: Arc<State>,
+        meter: &mut Meter,
+    ) -> Result<Option<Arc<State>>, Limit> {
+        let mut bound = initial_state.lower_bound();
+        loop {
+            self.table.fill(None);
+            self.table_occupied = 0;
+            self.path.insert(Packed::canonical(&initial_state));
+            let probe = self.search(&initial_state, bound, meter)?;
+            self.path.clear();
+
+            match probe {
+                Probe::Found(goal) => return Ok(Some(goal)),
+                Probe::Exceeded(Some(next_bound)) => bound = next_bound,
+                Probe::Exceeded(None) => return Ok(None),
+            }
+        }
+    }
+
+    fn search(
+        &mut self,
+        state: &Arc<State>,
+        bound: usize,
+        meter: &mut Meter,
+    ) -> Result<Probe, Limit> {
+        let f = state.step + state.lower_bound();
+        if f > bound {
+            return Ok(Probe::Exceeded(Some(f)));
+        }
+        if state.card_count == 0 {
+            return Ok(Probe::Found(state.clone()));
+        }
+
+        meter.expand(self.path.len() + self.table_occupied)?;
+
+        let mut next_bound = None;
+        let actions = state.valid_actions().into_iter();
+        for action in actions.chain(state.valid_slot_actions()) {
+            let new_state = state.transit(&action);
+            let key = Packed::canonical(&new_state);
+            if self.path.contains(&key) || self.seen(&key, new_state.step) {
+                continue;
+            }
+
+            self.path.insert(key);
+            let probe = self.search(&new_state, bound, meter)?;
+            self.path.remove(&key);
+
+            match probe {
+                Probe::Found(goal) => return Ok(Probe::Found(goal)),
+                Probe::Exceeded(Some(f)) => {
+                    next_bound = Some(next_bound.map_or(f, |bound: usize| bound.min(f)));
+                }
+                Probe::Exceeded(None) => {}
+            }
+        }
+
+        Ok(Probe::Exceeded(next_bound))
+    }
+}
+
+fn get_solution(state: &Arc<State>) -> (Vec<AutoMove>, Vec<Step>) {
     let mut solution = Vec::new();
     let mut current_state = state;
     while let Some(action) = current_state.action {
-        solution.push(action);
+        solution.push(Step {
+            action,
+            auto_moves: current_state.auto_moves.clone(),
+        });
         current_state = current_state.prev_state.as_ref().unwrap();
         // println!("{current_state}");
     }
     solution.reverse();
-    solution
+    (current_state.auto_moves.clone(), solution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{load, CLEARED, DEAL, ENDGAME, LOST};
+    use crate::io::load_boards;
+    use crate::rule::{Card, Color};
+
+    #[test]
+    fn test_a_star_finds_shortest_solution() {
+        let board = load(ENDGAME);
+
+        for mode in [SearchMode::AStar, SearchMode::IdaStar] {
+            let options = SolveOptions {
+                mode,
+                ..Default::default()
+            };
+            let solution = solve_with(&board, &options).unwrap();
+            assert_eq!(solution.steps.len(), 2);
+
+            // The eights, then both nines, go to the foundations by themselves.
+            let auto_moves: Vec<_> = solution
+                .steps
+                .iter()
+                .flat_map(|step| step.auto_moves.iter().map(|auto_move| auto_move.card))
+                .collect();
+            assert_eq!(auto_moves.len(), 4);
+            assert_eq!(auto_moves[0], Card::Number(Color::Red, 8));
+
+            let boards = solution.boards(&board);
+            assert_eq!(boards.len(), 2);
+            assert!(boards[1].trays.iter().all(Vec::is_empty));
+            assert_eq!(boards[1].foundations[&Color::Green], 9);
+        }
+
+        let solution = solve(&board).unwrap();
+        assert!(solution.steps.len() >= 2);
+    }
+
+    #[test]
+    fn test_ida_star_table_too_large() {
+        let options = SolveOptions {
+            mode: SearchMode::IdaStar,
+            transposition_table_size: usize::MAX,
+            ..Default::default()
+        };
+        assert!(matches!(
+            solve_with(&load(ENDGAME), &options),
+            Err(SolveError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_budget_gives_up() {
+        let board = load(DEAL);
+        let options = SolveOptions {
+            budget: Budget {
+                max_expanded: Some(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        match solve_with(&board, &options) {
+            Err(SolveError::BudgetExceeded { limit, stats }) => {
+                assert_eq!(limit, Limit::Expanded);
+                assert_eq!(stats.expanded, 10);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_prove() {
+        let options = SolveOptions {
+            mode: SearchMode::Prove,
+            ..Default::default()
+        };
+
+        let board = load(DEAL);
+        let solution = solve_with(&board, &options).unwrap();
+        let last = solution.boards(&board).pop().unwrap();
+        assert!(last.trays.iter().all(|tray| tray.is_empty()));
+
+        let board = load(LOST);
+        match solve_with(&board, &options) {
+            Err(SolveError::Unsolvable { positions, .. }) => assert_eq!(positions, 1),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parallel() {
+        let options = SolveOptions {
+            workers: 3,
+            ..Default::default()
+        };
+        let board = load(DEAL);
+        let solution = solve_with(&board, &options).unwrap();
+        let actions: Vec<_> = solution.actions().collect();
+        assert!(crate::verify::verify(&board, &actions).is_ok());
+
+        // The workers share the positions out, so together they examine every one exactly once.
+        let board = crate::generate::generate(257);
+        let positions = |workers| match solve_with(
+            &board,
+            &SolveOptions {
+                mode: SearchMode::Prove,
+                workers,
+                ..Default::default()
+            },
+        ) {
+            Err(SolveError::Unsolvable { positions, .. }) => positions,
+            other => panic!("unexpected result: {other:?}"),
+        };
+        assert_eq!(positions(3), positions(1));
+    }
+
+    #[test]
+    fn test_nan_estimate() {
+        #[derive(Debug)]
+        struct NotANumber;
+
+        impl Heuristic for NotANumber {
+            fn estimate(&self, _: &State) -> f64 {
+                f64::NAN
+            }
+        }
+
+        // A heuristic gone wrong orders the search badly, but cannot crash it.
+        let board = load(ENDGAME);
+        for workers in [1, 2] {
+            let options = SolveOptions {
+                heuristic: Arc::new(NotANumber),
+                workers,
+                ..Default::default()
+            };
+            assert!(solve_with(&board, &options).is_ok());
+        }
+    }
+
+    /// Solvable deals on which [`Successors::Pruned`] runs out of states.
+    const PRUNING_REGRESSIONS: &str = include_str!("../testdata/pruning_regressions.txt");
+
+    #[test]
+    fn test_pruning_regressions() {
+        let pruned = SolveOptions {
+            successors: Successors::Pruned,
+            ..Default::default()
+        };
+        for board in load_boards(PRUNING_REGRESSIONS).unwrap() {
+            assert!(matches!(
+                solve_with(&board, &pruned),
+                Err(SolveError::Exhausted(_))
+            ));
+
+            let solution = solve_with(&board, &SolveOptions::default()).unwrap();
+            let last = solution.boards(&board).pop().unwrap();
+            assert!(last.trays.iter().all(|tray| tray.is_empty()));
+        }
+    }
+
+    #[test]
+    fn test_solve_empty_board() {
+        let board = load(CLEARED);
+        let solution = solve(&board).unwrap();
+        assert!(solution.steps.is_empty());
+    }
 }
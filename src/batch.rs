@@ -0,0 +1,206 @@
+use crate::algo::{solve_with, Solution, SolveError, SolveOptions};
+use crate::state::Board;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// The outcome of one deal of a batch.
+#[derive(Clone, Debug)]
+pub struct BatchResult {
+    pub name: String,
+    pub result: Result<Solution, SolveError>,
+}
+
+/// Solves every deal with `options`, on `threads` threads at once. Results are in the order of
+/// `deals`.
+pub fn solve_batch(
+    deals: &[(String, Board)],
+    options: &SolveOptions,
+    threads: usize,
+) -> Vec<BatchResult> {
+    let next_deal = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; deals.len()]);
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| loop {
+                let index = next_deal.fetch_add(1, Ordering::Relaxed);
+                let Some((name, board)) = deals.get(index) else {
+                    break;
+                };
+                let result = BatchResult {
+                    name: name.clone(),
+                    result: solve_with(board, options),
+                };
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(Option::unwrap)
+        .collect()
+}
+
+/// Mean and percentiles of a quantity over the deals of a batch.
+#[derive(Clone, Copy, Debug)]
+pub struct Distribution {
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+    fn new(mut values: Vec<f64>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+
+        // Nearest-rank percentile.
+        let percentile = |p: f64| {
+            let rank = (p / 100. * values.len() as f64).ceil() as usize;
+            values[rank.clamp(1, values.len()) - 1]
+        };
+        Some(Self {
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            p50: percentile(50.),
+            p90: percentile(90.),
+            p99: percentile(99.),
+            max: values[values.len() - 1],
+        })
+    }
+}
+
+/// Aggregate statistics of a batch.
+#[derive(Clone, Debug)]
+pub struct BatchSummary {
+    pub deals: usize,
+    pub solved: usize,
+    /// Deals whose search space was exhausted, by a search that skips some moves or by IDA*.
+    pub unsolved: usize,
+    /// Deals proven to have no solution.
+    pub unsolvable: usize,
+    /// Deals whose search hit its budget.
+    pub gave_up: usize,
+    pub invalid: usize,
+    /// Number of moves of the solved deals.
+    pub moves: Option<Distribution>,
+    /// Number of expanded states, over every searched deal.
+    pub expanded: Option<Distribution>,
+    /// Search time in milliseconds, over every searched deal.
+    pub elapsed_ms: Option<Distribution>,
+}
+
+impl BatchSummary {
+    pub fn new(results: &[BatchResult]) -> Self {
+        let mut summary = Self {
+            deals: results.len(),
+            solved: 0,
+            unsolved: 0,
+            unsolvable: 0,
+            gave_up: 0,
+            invalid: 0,
+            moves: None,
+            expanded: None,
+            elapsed_ms: None,
+        };
+
+        let mut moves = Vec::new();
+        let mut expanded = Vec::new();
+        let mut elapsed_ms = Vec::new();
+        for BatchResult { result, .. } in results {
+            let stats = match result {
+                Ok(solution) => {
+                    summary.solved += 1;
+                    moves.push(solution.steps.len() as f64);
+                    solution.stats
+                }
+                Err(SolveError::Exhausted(stats)) => {
+                    summary.unsolved += 1;
+                    *stats
+                }
+                Err(SolveError::Unsolvable { stats, .. }) => {
+                    summary.unsolvable += 1;
+                    *stats
+                }
+                Err(SolveError::BudgetExceeded { stats, .. }) => {
+                    summary.gave_up += 1;
+                    *stats
+                }
+                Err(SolveError::InvalidInput(_)) => {
+                    summary.invalid += 1;
+                    continue;
+                }
+            };
+            expanded.push(stats.expanded as f64);
+            elapsed_ms.push(stats.elapsed.as_secs_f64() * 1000.);
+        }
+
+        summary.moves = Distribution::new(moves);
+        summary.expanded = Distribution::new(expanded);
+        summary.elapsed_ms = Distribution::new(elapsed_ms);
+        summary
+    }
+
+    /// Fraction of the deals that were solved.
+    pub fn solve_rate(&self) -> f64 {
+        if self.deals == 0 {
+            0.
+        } else {
+            self.solved as f64 / self.deals as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::generate;
+
+    #[test]
+    fn test_solve_batch() {
+        let deals: Vec<_> = (0..4)
+            .map(|seed| (format!("seed {seed}"), generate(seed)))
+            .collect();
+        let mut invalid = generate(4);
+        invalid.trays[0].pop();
+        let deals = [deals, vec![("invalid".to_string(), invalid)]].concat();
+
+        let mut options = SolveOptions::default();
+        options.budget.max_expanded = Some(2000);
+        let results = solve_batch(&deals, &options, 3);
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[2].name, "seed 2");
+
+        let summary = BatchSummary::new(&results);
+        assert_eq!(summary.deals, 5);
+        assert_eq!(summary.invalid, 1);
+        assert_eq!(
+            summary.solved
+                + summary.unsolved
+                + summary.unsolvable
+                + summary.gave_up
+                + summary.invalid,
+            5
+        );
+        assert!(summary.expanded.is_some());
+    }
+
+    #[test]
+    fn test_distribution() {
+        let distribution = Distribution::new((1..=100).map(f64::from).collect()).unwrap();
+        assert_eq!(distribution.mean, 50.5);
+        assert_eq!(distribution.p50, 50.);
+        assert_eq!(distribution.p90, 90.);
+        assert_eq!(distribution.p99, 99.);
+        assert_eq!(distribution.max, 100.);
+        assert!(Distribution::new(Vec::new()).is_none());
+    }
+}
@@ -1,4 +1,4 @@
-pub(crate) const SLOT_COUNT: usize = 3;
-pub(crate) const TRAY_COUNT: usize = 8;
-pub(crate) const DRAGON_COUNT: usize = 4;
+pub const SLOT_COUNT: usize = 3;
+pub const TRAY_COUNT: usize = 8;
+pub const DRAGON_COUNT: usize = 4;
 
@@ -0,0 +1,35 @@
+//! Boards shared by the tests of several modules.
+
+use crate::io::load_board;
+use crate::state::Board;
+
+/// A full deal, which the default search solves quickly.
+pub(crate) const DEAL: &str = "\
+r1 rd g4 b4 rd
+g7 bd gd r2 b3
+r8 r6 g2 g5 g6
+bd b5 f b6 g9
+r9 bd r4 gd g1
+r5 bd r7 rd b1
+g3 rd b2 b8 gd
+gd g8 r3 b9 b7
+";
+
+/// Both nines have to make way for the eights below them, which then clear on their own. Two
+/// actions solve it, and the trays and slots are otherwise empty or collapsed.
+pub(crate) const ENDGAME: &str = "r8 r9\ng8 g9\nslots: x x x\nfoundations: r7 g7 b9\nflower: f\n";
+
+/// Every tray and every slot is blocked from the start, so no action is legal.
+pub(crate) const LOST: &str = "\
+r7 r8\ng7 g8\nb7 b8\nr9 rd\ng9 rd\nb9 gd\nrd gd\ngd bd bd bd
+slots: rd gd bd
+foundations: r6 g6 b6
+flower: f
+";
+
+/// A board with every card on the foundations.
+pub(crate) const CLEARED: &str = "slots: x x x\nfoundations: r9 g9 b9\nflower: f\n";
+
+pub(crate) fn load(input: &str) -> Board {
+    load_board(input).unwrap()
+}
@@ -0,0 +1,169 @@
+use crate::rule::{
+    is_legal, validate_game, Action, AutoMove, Color, IllegalMove, Place, SLOT_COUNT, TRAY_COUNT,
+};
+use crate::state::{Board, State};
+
+use std::sync::Arc;
+
+/// A game played one action at a time, with undo and redo.
+pub struct Game {
+    /// Every position of the game so far, the deal first, including undone ones.
+    history: Vec<Arc<State>>,
+    /// Index of the current position in `history`.
+    current: usize,
+}
+
+impl Game {
+    /// Starts a game on `board`, which must pass [`validate_game`]. The game moves cards by
+    /// itself right away if it can.
+    pub fn new(board: &Board) -> Result<Self, String> {
+        validate_game(board)?;
+        Ok(Self {
+            history: vec![Arc::new(State::with_board(board))],
+            current: 0,
+        })
+    }
+
+    fn state(&self) -> &Arc<State> {
+        &self.history[self.current]
+    }
+
+    pub fn board(&self) -> Board {
+        self.state().to_board()
+    }
+
+    /// Cards the game moved by itself before the first action.
+    pub fn initial_auto_moves(&self) -> &[AutoMove] {
+        &self.history[0].auto_moves
+    }
+
+    /// Takes `action` and returns the cards the game then moved by itself. Actions undone before
+    /// can no longer be redone.
+    pub fn play(&mut self, action: &Action) -> Result<&[AutoMove], IllegalMove> {
+        let state = self.state().try_transit(action)?;
+        self.history.truncate(self.current + 1);
+        self.history.push(state);
+        self.current += 1;
+        Ok(&self.state().auto_moves)
+    }
+
+    /// Takes back the last action, if any, and returns it.
+    pub fn undo(&mut self) -> Option<Action> {
+        let action = self.state().action?;
+        self.current -= 1;
+        Some(action)
+    }
+
+    /// Takes again the last undone action, if any, and returns it.
+    pub fn redo(&mut self) -> Option<Action> {
+        let state = self.history.get(self.current + 1)?;
+        self.current += 1;
+        state.action
+    }
+
+    /// Actions taken so far, the undone ones left out.
+    pub fn actions(&self) -> impl Iterator<Item = Action> + '_ {
+        self.history[1..=self.current]
+            .iter()
+            .filter_map(|state| state.action)
+    }
+
+    /// Every action that can be taken in the current position, as checked by [`is_legal`].
+    pub fn legal_actions(&self) -> Vec<Action> {
+        let state = self.state();
+        let places = || {
+            (0..TRAY_COUNT)
+                .map(Place::Tray)
+                .chain((0..SLOT_COUNT).map(Place::Slot))
+        };
+
+        let mut actions = Vec::new();
+        for src in places() {
+            actions.push(Action::Pop { src });
+            let height = match src {
+                Place::Tray(index) => state.trays[index].len(),
+                Place::Slot(_) => 1,
+            };
+            for dest in places() {
+                for count in 1..=height {
+                    actions.push(Action::Move { src, dest, count });
+                }
+            }
+        }
+        actions.extend(Color::values().map(Action::CollapseDragon));
+
+        actions.retain(|action| is_legal(state, action).is_ok());
+        actions
+    }
+
+    pub fn is_won(&self) -> bool {
+        self.state().card_count == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{load, ENDGAME};
+    use crate::rule::{Card, Color, Place};
+
+    #[test]
+    fn test_game() {
+        let board = load(ENDGAME);
+        let mut game = Game::new(&board).unwrap();
+        assert!(game.initial_auto_moves().is_empty());
+
+        let illegal = "t1>t2".parse::<Action>().unwrap();
+        assert_eq!(game.play(&illegal), Err(IllegalMove::WrongNumber));
+        assert_eq!(game.actions().count(), 0);
+
+        let first = "t1>t3".parse::<Action>().unwrap();
+        let auto_moves = game.play(&first).unwrap();
+        assert_eq!(
+            auto_moves,
+            [AutoMove {
+                card: Card::Number(Color::Red, 8),
+                src: Place::Tray(0),
+            }]
+        );
+        assert_eq!(game.undo(), Some(first));
+        assert_eq!(game.undo(), None);
+        assert_eq!(game.redo(), Some(first));
+        assert_eq!(game.redo(), None);
+
+        let second = "t2>t1".parse::<Action>().unwrap();
+        game.play(&second).unwrap();
+        assert!(game.is_won());
+        assert_eq!(game.actions().collect::<Vec<_>>(), [first, second]);
+        assert!(game.legal_actions().is_empty());
+
+        // Playing after an undo drops the undone actions.
+        game.undo();
+        game.undo();
+        let other = "t2>t3".parse::<Action>().unwrap();
+        game.play(&other).unwrap();
+        assert_eq!(game.redo(), None);
+        assert_eq!(game.actions().collect::<Vec<_>>(), [other]);
+    }
+
+    #[test]
+    fn test_legal_actions() {
+        let board = load(
+            "g9 r8\nr6 r7\ngd gd gd gd\nbd bd bd bd\n\
+             slots: x r9 -\nfoundations: r5 g8 b9\nflower: f\n",
+        );
+        let game = Game::new(&board).unwrap();
+        let actions: Vec<_> = game
+            .legal_actions()
+            .iter()
+            .map(Action::to_notation)
+            .collect();
+        // Moving a whole tray to an empty one and moving between slots are legal too.
+        assert_eq!(
+            actions.join(" "),
+            "t1>t5 t1>t5x2 t1>t6 t1>t6x2 t1>t7 t1>t7x2 t1>t8 t1>t8x2 t1>s3 \
+             t2>t5 t2>t6 t2>t7 t2>t8 t2>s3 t3>t5 t3>t6 t3>t7 t3>t8 t3>s3 \
+             t4>t5 t4>t6 t4>t7 t4>t8 t4>s3 s2>t5 s2>t6 s2>t7 s2>t8 s2>s3"
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use crate::algo::{solve_with, SolveOptions};
+use crate::rule::{Card, Color, DRAGON_COUNT, TRAY_COUNT};
+use crate::state::Board;
+
+/// Number of cards dealt to each tray.
+const DEAL_SIZE: usize = 5;
+
+/// SplitMix64, which is small and good enough to shuffle a deck reproducibly across platforms.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniformly distributed number in `0..bound`.
+    fn below(&mut self, bound: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return value % bound;
+            }
+        }
+    }
+}
+
+/// Deals a uniformly shuffled deck across the trays. The same seed always gives the same deal.
+pub fn generate(seed: u64) -> Board {
+    let mut deck = Vec::new();
+    for color in Color::values() {
+        deck.extend((1..=9).map(|number| Card::Number(color, number)));
+        deck.extend([Card::Dragon(color); DRAGON_COUNT]);
+    }
+    deck.push(Card::Flower);
+
+    let mut rng = Rng(seed);
+    for i in (1..deck.len()).rev() {
+        let j = rng.below(i as u64 + 1) as usize;
+        deck.swap(i, j);
+    }
+
+    let mut board = Board::default();
+    for (tray, cards) in board.trays.iter_mut().zip(deck.chunks(DEAL_SIZE)) {
+        tray.extend_from_slice(cards);
+    }
+    debug_assert_eq!(deck.len(), TRAY_COUNT * DEAL_SIZE);
+    board
+}
+
+/// Deals with [`generate`] from `seed` onwards until the solver clears the deal within the
+/// budget of `options`. Returns the seed of the deal along with it, or `None` if none of the
+/// first `attempts` deals was cleared.
+pub fn generate_solvable(
+    seed: u64,
+    options: &SolveOptions,
+    attempts: usize,
+) -> Option<(u64, Board)> {
+    let mut seed = seed;
+    for _ in 0..attempts {
+        let board = generate(seed);
+        if solve_with(&board, options).is_ok() {
+            return Some((seed, board));
+        }
+        seed = seed.wrapping_add(1);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{format_board, load_board};
+    use crate::rule::validate_game;
+
+    #[test]
+    fn test_generate() {
+        let board = generate(42);
+        assert!(validate_game(&board).is_ok());
+        assert!(board.trays.iter().all(|tray| tray.len() == DEAL_SIZE));
+
+        assert_eq!(generate(42), board);
+        assert_ne!(generate(43), board);
+
+        assert_eq!(load_board(&format_board(&board)).unwrap(), board);
+    }
+
+    #[test]
+    fn test_generate_solvable() {
+        let mut options = SolveOptions::default();
+        let (seed, board) = generate_solvable(42, &options, 10).unwrap();
+        assert!(seed >= 42);
+        assert_eq!(generate(seed), board);
+
+        options.budget.max_expanded = Some(0);
+        assert_eq!(generate_solvable(42, &options, 10), None);
+    }
+}
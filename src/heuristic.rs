@@ -0,0 +1,163 @@
+use crate::rule::{can_be_stacked, Card};
+use crate::state::State;
+
+use std::fmt::Debug;
+use std::str::FromStr;
+
+/// Estimates how far a position is from being cleared. Best-first searches expand the position
+/// with the lowest estimate first.
+pub trait Heuristic: Send + Sync + Debug {
+    fn estimate(&self, state: &State) -> f64;
+}
+
+/// The cards left plus a tenth of the actions taken, less one for every card stacked on the card
+/// under it. Trays holding a single run earn a tenth more per stacked card, and two tenths when the
+/// run starts from a nine.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Classic;
+
+impl Heuristic for Classic {
+    fn estimate(&self, state: &State) -> f64 {
+        let mut priority_of_cards = 0.;
+
+        for tray in state.trays() {
+            if tray.is_empty() {
+                continue;
+            }
+
+            let mut cards_stacked_cur_tray = 0;
+
+            for i in 1..tray.len() {
+                if can_be_stacked(tray[i], tray[i - 1]) {
+                    cards_stacked_cur_tray += 1;
+                }
+            }
+
+            if cards_stacked_cur_tray == tray.len() - 1 {
+                if let Card::Number(_, 9) = tray[0] {
+                    priority_of_cards += cards_stacked_cur_tray as f64 * 1.2;
+                } else {
+                    priority_of_cards += cards_stacked_cur_tray as f64 * 1.1;
+                }
+            } else {
+                priority_of_cards += cards_stacked_cur_tray as f64;
+            }
+        }
+
+        state.card_count() as f64 + state.step() as f64 * 0.1 - priority_of_cards
+    }
+}
+
+/// The actions taken plus [`State::lower_bound`], the fewest still needed. It never overestimates,
+/// which is what A* and IDA* rely on.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct LowerBound;
+
+impl Heuristic for LowerBound {
+    fn estimate(&self, state: &State) -> f64 {
+        (state.step() + state.lower_bound()) as f64
+    }
+}
+
+/// The cards covering, in each tray, the deepest card the game waits for: the next card of its
+/// foundation or a dragon.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct BlockedCards;
+
+impl Heuristic for BlockedCards {
+    fn estimate(&self, state: &State) -> f64 {
+        let mut blocked = 0;
+        for tray in state.trays() {
+            let waited = tray.iter().position(|&card| match card {
+                Card::Number(color, number) => number == state.foundation(color) + 1,
+                Card::Dragon(_) => true,
+                _ => false,
+            });
+            if let Some(depth) = waited {
+                blocked += tray.len() - depth - 1;
+            }
+        }
+        blocked as f64
+    }
+}
+
+/// A weighted sum of [`Classic`], [`LowerBound`] and [`BlockedCards`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Weighted {
+    pub classic: f64,
+    pub lower_bound: f64,
+    pub blocked_cards: f64,
+}
+
+impl Heuristic for Weighted {
+    fn estimate(&self, state: &State) -> f64 {
+        let mut estimate = 0.;
+        if self.classic != 0. {
+            estimate += self.classic * Classic.estimate(state);
+        }
+        if self.lower_bound != 0. {
+            estimate += self.lower_bound * LowerBound.estimate(state);
+        }
+        if self.blocked_cards != 0. {
+            estimate += self.blocked_cards * BlockedCards.estimate(state);
+        }
+        estimate
+    }
+}
+
+impl FromStr for Weighted {
+    type Err = String;
+
+    /// Parses the weights of [`Classic`], [`LowerBound`] and [`BlockedCards`], in this order and
+    /// separated by commas, as in `1,0.5,0`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("Invalid weights: {s}. Expected three finite numbers");
+        let weights = s
+            .split(',')
+            .map(|weight| match weight.trim().parse::<f64>() {
+                Ok(weight) if weight.is_finite() => Ok(weight),
+                _ => Err(error()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        match weights[..] {
+            [classic, lower_bound, blocked_cards] => Ok(Weighted {
+                classic,
+                lower_bound,
+                blocked_cards,
+            }),
+            _ => Err(error()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::load_board;
+
+    #[test]
+    fn test_heuristics() {
+        let board = load_board("r8 r9\ng8 g9 rd\nslots: - x x\nfoundations: r7 g7 b9\nflower: f\n")
+            .unwrap();
+        let state = State::with_board(&board);
+
+        assert_eq!(Classic.estimate(&state), 5.);
+        assert_eq!(LowerBound.estimate(&state), 3.);
+        // Each eight is under its nine, and the green one under the red dragon as well.
+        assert_eq!(BlockedCards.estimate(&state), 3.);
+
+        let weighted: Weighted = "1, 0.5,2".parse().unwrap();
+        assert_eq!(weighted.estimate(&state), 5. + 1.5 + 6.);
+        assert!("1,2".parse::<Weighted>().is_err());
+        assert!("1,x,2".parse::<Weighted>().is_err());
+        assert!("nan,1,1".parse::<Weighted>().is_err());
+        assert!("inf,0,-inf".parse::<Weighted>().is_err());
+
+        // A tray holding a run from a nine, and one with a run of two between other cards.
+        let board =
+            load_board("g9 r8 g7\ng6 g8 r7 r9\nslots: x x x\nfoundations: r6 g5 b9\nflower: f\n")
+                .unwrap();
+        let state = State::with_board(&board);
+        assert!((Classic.estimate(&state) - (7. - 2. * 1.2 - 1.)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::algo::{solve_with, SolveError, SolveOptions, Stats};
+use crate::rule::{Action, AutoMove, Card, Color, Place};
+use crate::state::{Board, State};
+
+use std::sync::Arc;
+
+/// The next action of a solution from a position, as found by [`hint`].
+#[derive(Clone, Debug)]
+pub struct Hint {
+    pub action: Action,
+    /// Moves the game makes by itself right after the action.
+    pub auto_moves: Vec<AutoMove>,
+    /// What the action achieves, most important first.
+    pub reasons: Vec<Reason>,
+    /// Number of actions of the solution, this one included.
+    pub solution_length: usize,
+    pub stats: Stats,
+}
+
+/// Something an action achieves, which tells why it is worth taking.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Reason {
+    /// The four dragons of the color go into a slot for good.
+    Collapse(Color),
+    /// The card goes on its foundation.
+    Foundation(Card),
+    /// The game then moves this many cards to the foundations by itself.
+    AutoMoves(usize),
+    /// The tray or slot is left empty.
+    Empties(Place),
+    /// The card is left on top of its tray.
+    Exposes(Card, Place),
+}
+
+/// Solves the position on `board` and returns the first action of the solution, with why it helps.
+///
+/// Returns `Ok(None)` if the board is already cleared, and [`SolveError::Unsolvable`] if the
+/// search proved the position lost.
+pub fn hint(board: &Board, options: &SolveOptions) -> Result<Option<Hint>, SolveError> {
+    let solution = solve_with(board, options)?;
+    let Some(step) = solution.steps.first() else {
+        return Ok(None);
+    };
+
+    let state = Arc::new(State::with_board(board));
+    let next_state = state.transit(&step.action);
+    Ok(Some(Hint {
+        action: step.action,
+        auto_moves: step.auto_moves.clone(),
+        reasons: explain(&state, &next_state, &step.action),
+        solution_length: solution.steps.len(),
+        stats: solution.stats,
+    }))
+}
+
+fn explain(state: &State, next_state: &State, action: &Action) -> Vec<Reason> {
+    let mut reasons = Vec::new();
+
+    let src = match *action {
+        Action::CollapseDragon(color) => {
+            reasons.push(Reason::Collapse(color));
+            None
+        }
+        Action::Pop { src } => {
+            if let Some(card) = top_card(state, src) {
+                reasons.push(Reason::Foundation(card));
+            }
+            Some(src)
+        }
+        Action::Move { src, .. } => Some(src),
+    };
+
+    let auto_moves = next_state
+        .auto_moves
+        .iter()
+        .filter(|auto_move| auto_move.card != Card::Flower)
+        .count();
+    if auto_moves > 0 {
+        reasons.push(Reason::AutoMoves(auto_moves));
+    }
+
+    if let Some(src) = src {
+        match top_card(next_state, src) {
+            None => reasons.push(Reason::Empties(src)),
+            Some(card) => reasons.push(Reason::Exposes(card, src)),
+        }
+    }
+
+    reasons
+}
+
+fn top_card(state: &State, place: Place) -> Option<Card> {
+    match place {
+        Place::Tray(index) => state.trays[index].last().copied(),
+        Place::Slot(index) => state.slots[index],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::SearchMode;
+    use crate::fixtures::{load, CLEARED, ENDGAME, LOST};
+
+    #[test]
+    fn test_hint() {
+        let board = load(ENDGAME);
+        let first = hint(&board, &SolveOptions::default()).unwrap().unwrap();
+        assert_eq!(first.solution_length, 2);
+        assert!(matches!(
+            first.action,
+            Action::Move {
+                src: Place::Tray(0) | Place::Tray(1),
+                ..
+            }
+        ));
+        assert_eq!(first.reasons[0], Reason::AutoMoves(1));
+
+        let lost = load(LOST);
+        let options = SolveOptions {
+            mode: SearchMode::Prove,
+            ..SolveOptions::default()
+        };
+        assert!(matches!(
+            hint(&lost, &options),
+            Err(SolveError::Unsolvable { positions: 1, .. })
+        ));
+
+        let cleared = load(CLEARED);
+        assert!(hint(&cleared, &SolveOptions::default()).unwrap().is_none());
+    }
+}
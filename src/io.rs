@@ -1,8 +1,16 @@
-use crate::rule::{Action, Card, Color, Place, SLOT_COUNT, TRAY_COUNT};
-use crate::state::State;
+use crate::algo::{Limit, Solution, SolveError, Stats};
+use crate::batch::{BatchResult, BatchSummary, Distribution};
+use crate::game::Game;
+use crate::hint::{Hint, Reason};
+use crate::render::{render_board, Style};
+use crate::rule::{Action, AutoMove, Card, Color, IllegalMove, Place, SLOT_COUNT, TRAY_COUNT};
+use crate::state::{Board, State};
+use crate::verify::VerifyError;
 
 use colored::Colorize;
 use std::fmt;
+use std::io::{BufRead, Write};
+use std::sync::Arc;
 
 impl fmt::Display for Action {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -22,6 +30,22 @@ impl fmt::Display for Action {
     }
 }
 
+impl fmt::Display for AutoMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dest = match self.card {
+            Card::Flower => "flower slot",
+            _ => "foundation",
+        };
+        write!(
+            f,
+            "{} {} from {} to {dest}",
+            "(auto)".dimmed(),
+            self.card,
+            self.src
+        )
+    }
+}
+
 impl fmt::Display for Card {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -52,53 +76,1195 @@ impl fmt::Display for Place {
     }
 }
 
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reason::Collapse(color) => write!(f, "frees the {color} dragons for good"),
+            Reason::Foundation(card) => write!(f, "plays {card} on its foundation"),
+            Reason::AutoMoves(1) => write!(f, "lets the game move a card to the foundations"),
+            Reason::AutoMoves(count) => {
+                write!(f, "lets the game move {count} cards to the foundations")
+            }
+            Reason::Empties(place) => write!(f, "empties {place}"),
+            Reason::Exposes(card, place) => write!(f, "exposes {card} in {place}"),
+        }
+    }
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolveError::InvalidInput(err) => write!(f, "Invalid input: {err}"),
+            SolveError::Exhausted(stats) => write!(
+                f,
+                "No solution found after {expanded} iterations ({elapsed:.2?})",
+                expanded = stats.expanded,
+                elapsed = stats.elapsed,
+            ),
+            SolveError::Unsolvable { positions, stats } => write!(
+                f,
+                "No solution exists: all {positions} reachable positions examined \
+                 in {expanded} iterations ({elapsed:.2?})",
+                expanded = stats.expanded,
+                elapsed = stats.elapsed,
+            ),
+            SolveError::BudgetExceeded { limit, stats } => write!(
+                f,
+                "Gave up after {expanded} iterations ({elapsed:.2?}): {limit} limit reached",
+                expanded = stats.expanded,
+                elapsed = stats.elapsed,
+            ),
+        }
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::InvalidInput(err) => write!(f, "Invalid game state: {err}"),
+            VerifyError::IllegalAction {
+                step,
+                action,
+                reason,
+            } => write!(f, "Step {step}: illegal action: {action}: {reason}"),
+            VerifyError::NotCleared { cards_left } => {
+                write!(
+                    f,
+                    "{cards_left} card(s) left on the board after the last step"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IllegalMove::NoSuchPlace(place) => write!(f, "{place} does not exist"),
+            IllegalMove::EmptySource => write!(f, "there is no card to take"),
+            IllegalMove::NoCards => write!(f, "no card is moved"),
+            IllegalMove::TooManyCards => write!(f, "too many cards"),
+            IllegalMove::NotARun => write!(f, "the cards are not a run"),
+            IllegalMove::WrongColor => write!(f, "cannot stack cards of the same color"),
+            IllegalMove::WrongNumber => write!(f, "cards must be stacked in descending order"),
+            IllegalMove::NotStackable => write!(f, "only number cards can be stacked"),
+            IllegalMove::SamePlace => write!(f, "the source and destination are the same"),
+            IllegalMove::SlotOccupied => write!(f, "the slot is occupied"),
+            IllegalMove::CollapsedSlot => write!(f, "collapsed dragons cannot be moved"),
+            IllegalMove::NotNextOnFoundation => write!(f, "not the next card of its foundation"),
+            IllegalMove::DragonsNotExposed => write!(f, "not all four dragons are exposed"),
+            IllegalMove::DragonSlotUnavailable => write!(f, "no slot is available for the dragons"),
+        }
+    }
+}
+
+impl std::error::Error for IllegalMove {}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Expanded => write!(f, "node"),
+            Limit::Time => write!(f, "time"),
+            Limit::Stored => write!(f, "memory"),
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
 impl fmt::Display for State {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut s = String::new();
-        s.push_str("[ ");
-        for slot in self.slots.iter() {
-            match slot {
-                Some(card) => s.push_str(&format!("{card} ")),
-                None => s.push_str("() "),
+        write!(
+            f,
+            "{}",
+            format_state(self, &Highlight::default(), Style::Ascii)
+        )
+    }
+}
+
+/// The cards an action has just moved, as they are found on the board after it.
+#[derive(Default)]
+struct Highlight {
+    /// A tray and the number of cards on top of it.
+    tray: Option<(usize, usize)>,
+    slot: Option<usize>,
+    foundation: Option<Color>,
+}
+
+impl Highlight {
+    fn new(prev_state: &State, state: &State, action: &Action) -> Self {
+        let mut highlight = Highlight::default();
+        match *action {
+            Action::Move {
+                dest: Place::Tray(index),
+                count,
+                ..
+            } => {
+                // Some of the moved cards may have gone on to the foundations right away.
+                let removed = state
+                    .auto_moves
+                    .iter()
+                    .filter(|auto_move| auto_move.src == Place::Tray(index))
+                    .count();
+                highlight.tray = Some((index, count.saturating_sub(removed)));
+            }
+            Action::Move {
+                dest: Place::Slot(index),
+                ..
+            } => highlight.slot = Some(index),
+            Action::Pop { src } => {
+                let card = match src {
+                    Place::Tray(index) => prev_state.trays[index].last().copied(),
+                    Place::Slot(index) => prev_state.slots[index],
+                };
+                if let Some(Card::Number(color, _)) = card {
+                    highlight.foundation = Some(color);
+                }
+            }
+            Action::CollapseDragon(_) => {
+                highlight.slot = (0..SLOT_COUNT).find(|&index| {
+                    state.slots[index] == Some(Card::CollapsedDragon)
+                        && prev_state.slots[index] != Some(Card::CollapsedDragon)
+                });
             }
         }
-        s.push_str("]\n");
-        for (i, tray) in self.trays.iter().enumerate() {
-            s.push_str(&format!("Tray {i}: ", i = i + 1));
-            for card in tray.iter() {
-                s.push_str(&format!("{card} "));
+        highlight
+    }
+}
+
+/// Writes the state, with the highlighted cards in reverse video in [`Style::Color`] and between
+/// asterisks in [`Style::Ascii`].
+fn format_state(state: &State, highlight: &Highlight, style: Style) -> String {
+    let mark = |card: String, highlighted: bool| match (highlighted, style) {
+        (false, _) => card,
+        (true, Style::Color) => card.reversed().to_string(),
+        (true, Style::Ascii) => format!("*{card}*"),
+    };
+
+    let mut s = String::new();
+    s.push_str("[ ");
+    for (i, slot) in state.slots.iter().enumerate() {
+        match slot {
+            Some(card) => {
+                s.push_str(&mark(card.to_string(), highlight.slot == Some(i)));
+                s.push(' ');
             }
-            s.push('\n');
+            None => s.push_str("() "),
         }
-        write!(f, "{s}")
     }
+    s.push_str("] ");
+    s.push_str(if state.flower { "(Flower) " } else { "() " });
+    s.push_str("[ ");
+    for color in Color::values() {
+        match state.foundations[color as usize] {
+            0 => s.push_str("() "),
+            number => {
+                let card = Card::Number(color, number).to_string();
+                s.push_str(&mark(card, highlight.foundation == Some(color)));
+                s.push(' ');
+            }
+        }
+    }
+    s.push_str("]\n");
+    for (i, tray) in state.trays.iter().enumerate() {
+        s.push_str(&format!("Tray {i}: ", i = i + 1));
+        let moved = match highlight.tray {
+            Some((index, count)) if index == i => count,
+            _ => 0,
+        };
+        for (j, card) in tray.iter().enumerate() {
+            s.push_str(&mark(card.to_string(), j + moved >= tray.len()));
+            s.push(' ');
+        }
+        s.push('\n');
+    }
+    s
 }
 
-pub(crate) fn load_trays_and_slots(
-    input: String,
-) -> ([Vec<Card>; TRAY_COUNT], [Option<Card>; SLOT_COUNT]) {
-    let mut trays = [const { Vec::new() }; TRAY_COUNT];
-    let slots = [None; SLOT_COUNT];
+/// Reads a board from its text form.
+///
+/// Every plain line describes a tray, bottom card first. A game in progress adds lines of the
+/// form `key: values` anywhere in the input:
+///
+/// - `slots: r5 - x` lists the slots from left to right, with `-` for an empty slot and `x` for
+///   a collapsed dragon;
+/// - `foundations: r3 b1` lists the top card of each non-empty foundation;
+/// - `flower: f` tells that the flower is already placed.
+///
+/// Lines starting with `#` are comments.
+pub fn load_board(input: &str) -> Result<Board, String> {
+    let mut board = Board::default();
+    let mut tray_count = 0;
 
-    for (i, line) in input.lines().enumerate() {
-        if i < TRAY_COUNT {
-            trays[i] = line
+    for line in input.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            load_board_section(&mut board, key.trim(), value)?;
+            continue;
+        }
+
+        if tray_count < TRAY_COUNT {
+            board.trays[tray_count] = line
                 .split_whitespace()
-                .map(|s| s.parse::<Card>().expect("Invalid card format"))
-                .collect();
+                .map(|s| s.parse::<Card>())
+                .collect::<Result<_, _>>()?;
+            tray_count += 1;
+        } else if !line.trim().is_empty() {
+            return Err(format!("Too many trays, expected at most {TRAY_COUNT}"));
+        }
+    }
+
+    Ok(board)
+}
+
+/// Reads several boards separated by `---` lines.
+pub fn load_boards(input: &str) -> Result<Vec<Board>, String> {
+    let mut boards = Vec::new();
+    let mut current = String::new();
+
+    for line in input.lines().chain(std::iter::once("---")) {
+        if line.trim() == "---" {
+            if !current.trim().is_empty() {
+                boards.push(load_board(&current)?);
+            }
+            current.clear();
+        } else {
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+
+    Ok(boards)
+}
+
+/// Writes a board in the text form read by [`load_board`].
+pub fn format_board(board: &Board) -> String {
+    let mut s = String::new();
+    for tray in board.trays.iter() {
+        let tokens: Vec<String> = tray.iter().map(card_token).collect();
+        s.push_str(&tokens.join(" "));
+        s.push('\n');
+    }
+
+    if board.slots.iter().any(Option::is_some) {
+        let tokens: Vec<String> = board
+            .slots
+            .iter()
+            .map(|slot| slot.as_ref().map_or("-".to_string(), card_token))
+            .collect();
+        s.push_str(&format!("slots: {}\n", tokens.join(" ")));
+    }
+
+    let foundations: Vec<String> = Color::values()
+        .filter_map(|color| match board.foundations.get(&color) {
+            Some(&number) if number > 0 => Some(card_token(&Card::Number(color, number))),
+            _ => None,
+        })
+        .collect();
+    if !foundations.is_empty() {
+        s.push_str(&format!("foundations: {}\n", foundations.join(" ")));
+    }
+
+    if board.flower {
+        s.push_str("flower: f\n");
+    }
+
+    s
+}
+
+/// Writes a card as in the input format: `r3`, `gd`, `f`, or `x` for collapsed dragons.
+pub(crate) fn card_token(card: &Card) -> String {
+    match card {
+        Card::Number(color, number) => format!("{}{number}", color.to_notation()),
+        Card::Dragon(color) => format!("{}d", color.to_notation()),
+        Card::Flower => "f".to_string(),
+        Card::CollapsedDragon => "x".to_string(),
+    }
+}
+
+fn load_board_section(board: &mut Board, key: &str, value: &str) -> Result<(), String> {
+    match key {
+        "slots" => {
+            let tokens: Vec<&str> = value.split_whitespace().collect();
+            if tokens.len() > SLOT_COUNT {
+                return Err(format!("Too many slots, expected at most {SLOT_COUNT}"));
+            }
+            for (slot, token) in board.slots.iter_mut().zip(tokens) {
+                *slot = match token {
+                    "-" => None,
+                    "x" => Some(Card::CollapsedDragon),
+                    _ => Some(token.parse::<Card>()?),
+                };
+            }
+        }
+        "foundations" => {
+            for token in value.split_whitespace() {
+                match token.parse::<Card>()? {
+                    Card::Number(color, number) => {
+                        if board.foundations.insert(color, number).is_some() {
+                            return Err(format!("Duplicate {color} foundation"));
+                        }
+                    }
+                    card => return Err(format!("Invalid foundation card: {card}")),
+                }
+            }
+        }
+        "flower" => {
+            board.flower = match value.trim() {
+                "f" => true,
+                "-" | "" => false,
+                other => return Err(format!("Invalid flower slot: {other}")),
+            };
         }
+        _ => return Err(format!("Unknown section: {key}")),
     }
 
-    (trays, slots)
+    Ok(())
+}
+
+/// Reads the actions of a solution as printed by [`print_solution`]. Lines other than steps are
+/// ignored, and so are terminal colors.
+///
+/// Any input without steps is read as actions in compact notation instead (see
+/// [`Action::to_notation`]), separated by whitespace, with `#` starting a comment line.
+pub fn load_actions(input: &str) -> Result<Vec<Action>, String> {
+    let mut actions = Vec::new();
+
+    let lines: Vec<String> = input.lines().map(strip_colors).collect();
+    if !lines.iter().any(|line| line.trim().starts_with("Step ")) {
+        for line in lines.iter().filter(|line| !line.starts_with('#')) {
+            for token in line.split_whitespace() {
+                actions.push(token.parse::<Action>()?);
+            }
+        }
+        return Ok(actions);
+    }
+
+    for line in lines {
+        let Some(step) = line.trim().strip_prefix("Step ") else {
+            continue;
+        };
+        let Some((_, action)) = step.split_once(':') else {
+            return Err(format!("Invalid step: {line}"));
+        };
+        actions.push(parse_action(action.trim())?);
+    }
+
+    Ok(actions)
+}
+
+/// Writes actions in compact notation, one per line, as read by [`load_actions`].
+pub fn format_actions(actions: impl IntoIterator<Item = Action>) -> String {
+    actions
+        .into_iter()
+        .map(|action| action.to_notation() + "\n")
+        .collect()
+}
+
+pub(crate) fn strip_colors(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip a control sequence such as `\x1b[1;34m`.
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+fn parse_action(s: &str) -> Result<Action, String> {
+    let words: Vec<&str> = s.split_whitespace().collect();
+    match words.as_slice() {
+        ["Pop", "from", kind, index] => Ok(Action::Pop {
+            src: parse_place(kind, index)?,
+        }),
+        ["Move", count, "card" | "cards", "from", src_kind, src_index, "to", dest_kind, dest_index] => {
+            Ok(Action::Move {
+                src: parse_place(src_kind, src_index)?,
+                dest: parse_place(dest_kind, dest_index)?,
+                count: count
+                    .parse()
+                    .map_err(|_| format!("Invalid card count: {count}"))?,
+            })
+        }
+        ["Collapse", color, "Dragon"] => match *color {
+            "Red" => Ok(Action::CollapseDragon(Color::Red)),
+            "Green" => Ok(Action::CollapseDragon(Color::Green)),
+            "Black" => Ok(Action::CollapseDragon(Color::Black)),
+            _ => Err(format!("Invalid color: {color}")),
+        },
+        _ => Err(format!("Invalid action: {s}")),
+    }
+}
+
+fn parse_place(kind: &str, index: &str) -> Result<Place, String> {
+    let (place, count): (fn(usize) -> Place, usize) = match kind {
+        "Tray" => (Place::Tray, TRAY_COUNT),
+        "Slot" => (Place::Slot, SLOT_COUNT),
+        _ => return Err(format!("Invalid place: {kind}")),
+    };
+    match index.parse::<usize>() {
+        Ok(index) if (1..=count).contains(&index) => Ok(place(index - 1)),
+        _ => Err(format!("Invalid {kind} number: {index}")),
+    }
+}
+
+pub fn print_solution(solution: &Solution) {
+    print!("{}", format_solution(solution));
+}
+
+/// Writes the solution as [`print_solution`] prints it, which [`load_actions`] reads back.
+pub fn format_solution(solution: &Solution) -> String {
+    let mut s = solution_header(solution);
+
+    for auto_move in solution.initial_auto_moves.iter() {
+        s += &format!("          {auto_move}\n");
+    }
+    for (i, step) in solution.steps.iter().enumerate() {
+        s += &format!("Step {i:2}: {action}\n", i = i + 1, action = step.action);
+        for auto_move in step.auto_moves.iter() {
+            s += &format!("          {auto_move}\n");
+        }
+    }
+    s
 }
 
-pub(crate) fn print_solution(actions: &[Action], iteration_count: usize) {
+fn solution_header(solution: &Solution) -> String {
+    format!(
+        "Found solution of {step} step(s) in {expanded} iterations ({elapsed:.2?})\n",
+        step = solution.steps.len(),
+        expanded = solution.stats.expanded,
+        elapsed = solution.stats.elapsed,
+    )
+}
+
+/// Prints the solution with the board after each step, as written by [`format_solution_boards`].
+pub fn print_solution_boards(deal: &Board, solution: &Solution) {
+    print!("{}", format_solution_boards(deal, solution));
+}
+
+/// Writes the solution like [`format_solution`], followed by the board after each step, replayed
+/// from `deal`, with the cards moved by the step highlighted. Highlighted cards are in reverse
+/// video when the output is colored, and between asterisks otherwise.
+pub fn format_solution_boards(deal: &Board, solution: &Solution) -> String {
+    format_solution_boards_in(deal, solution, Style::detect())
+}
+
+fn format_solution_boards_in(deal: &Board, solution: &Solution, style: Style) -> String {
+    let mut s = solution_header(solution);
+
+    let mut state = Arc::new(State::with_board(deal));
+    for auto_move in solution.initial_auto_moves.iter() {
+        s += &format!("          {auto_move}\n");
+    }
+    s += &format!("{state}\n");
+
+    for (i, step) in solution.steps.iter().enumerate() {
+        let prev_state = state;
+        state = prev_state.transit(&step.action);
+
+        s += &format!("Step {i:2}: {action}\n", i = i + 1, action = step.action);
+        for auto_move in step.auto_moves.iter() {
+            s += &format!("          {auto_move}\n");
+        }
+        let highlight = Highlight::new(&prev_state, &state, &step.action);
+        s += &format!("{}\n", format_state(&state, &highlight, style));
+    }
+    s
+}
+
+/// Prints the outcome of [`hint`](crate::hint): the hinted action, or why there is none.
+pub fn print_hint(result: &Result<Option<Hint>, SolveError>) {
+    let hint = match result {
+        Ok(Some(hint)) => hint,
+        Ok(None) => return println!("The board is already cleared"),
+        Err(SolveError::Unsolvable { .. }) => {
+            return println!("The position is lost: no solution exists")
+        }
+        Err(SolveError::Exhausted(_)) => {
+            return println!(
+                "No solution found: use --mode prove to tell whether the position is lost"
+            )
+        }
+        Err(err) => return print_solve_error(err),
+    };
+
+    println!(
+        "{action} ({notation})",
+        action = hint.action,
+        notation = hint.action.to_notation(),
+    );
+    for reason in hint.reasons.iter() {
+        println!("  - {reason}");
+    }
+    for auto_move in hint.auto_moves.iter() {
+        println!("          {auto_move}");
+    }
     println!(
-        "Found solution of {step} step(s) in {iteration_count} iterations",
-        step = actions.len()
+        "Solution of {step} step(s) from here, found in {expanded} iterations ({elapsed:.2?})",
+        step = hint.solution_length,
+        expanded = hint.stats.expanded,
+        elapsed = hint.stats.elapsed,
+    );
+}
+
+pub fn print_solve_error(err: &SolveError) {
+    println!("{err}");
+}
+
+pub fn print_batch_summary(summary: &BatchSummary) {
+    println!(
+        "Solved {solved}/{deals} deal(s) ({rate:.1}%): {unsolvable} proven unsolvable, \
+         {unsolved} not solved, {gave_up} gave up, {invalid} invalid",
+        solved = summary.solved,
+        deals = summary.deals,
+        rate = summary.solve_rate() * 100.,
+        unsolvable = summary.unsolvable,
+        unsolved = summary.unsolved,
+        gave_up = summary.gave_up,
+        invalid = summary.invalid,
+    );
+
+    println!(
+        "{:<12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "", "mean", "p50", "p90", "p99", "max"
+    );
+    let rows = [
+        ("moves", summary.moves),
+        ("nodes", summary.expanded),
+        ("time (ms)", summary.elapsed_ms),
+    ];
+    for (name, distribution) in rows {
+        match distribution {
+            Some(d) => println!(
+                "{name:<12} {:>12.1} {:>12.1} {:>12.1} {:>12.1} {:>12.1}",
+                d.mean, d.p50, d.p90, d.p99, d.max
+            ),
+            None => println!("{name:<12} {:>12}", "-"),
+        }
+    }
+}
+
+fn result_status(result: &Result<Solution, SolveError>) -> &'static str {
+    match result {
+        Ok(_) => "solved",
+        Err(SolveError::Exhausted(_)) => "unsolved",
+        Err(SolveError::Unsolvable { .. }) => "unsolvable",
+        Err(SolveError::BudgetExceeded { .. }) => "gave_up",
+        Err(SolveError::InvalidInput(_)) => "invalid",
+    }
+}
+
+fn result_stats(result: &Result<Solution, SolveError>) -> Option<Stats> {
+    match result {
+        Ok(solution) => Some(solution.stats),
+        Err(SolveError::Exhausted(stats))
+        | Err(SolveError::Unsolvable { stats, .. })
+        | Err(SolveError::BudgetExceeded { stats, .. }) => Some(*stats),
+        Err(SolveError::InvalidInput(_)) => None,
+    }
+}
+
+/// Writes one line per deal with its status, number of moves and search counters.
+pub fn format_batch_csv(results: &[BatchResult]) -> String {
+    let mut s = String::from("name,status,moves,expanded,stored,elapsed_ms\n");
+    for BatchResult { name, result } in results {
+        let moves = result
+            .as_ref()
+            .map_or(String::new(), |solution| solution.steps.len().to_string());
+        let stats = result_stats(result).map_or(",,".to_string(), |stats| {
+            format!(
+                "{},{},{:.3}",
+                stats.expanded,
+                stats.stored,
+                stats.elapsed.as_secs_f64() * 1000.
+            )
+        });
+        s.push_str(&format!(
+            "{},{},{moves},{stats}\n",
+            csv_field(name),
+            result_status(result)
+        ));
+    }
+    s
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Writes the summary and the result of every deal as a JSON object.
+pub fn format_batch_json(results: &[BatchResult], summary: &BatchSummary) -> String {
+    let distribution = |distribution: Option<Distribution>| match distribution {
+        Some(d) => format!(
+            "{{\"mean\": {}, \"p50\": {}, \"p90\": {}, \"p99\": {}, \"max\": {}}}",
+            d.mean, d.p50, d.p90, d.p99, d.max
+        ),
+        None => "null".to_string(),
+    };
+
+    let mut s = String::from("{\n  \"summary\": {\n");
+    s.push_str(&format!("    \"deals\": {},\n", summary.deals));
+    s.push_str(&format!("    \"solved\": {},\n", summary.solved));
+    s.push_str(&format!("    \"unsolved\": {},\n", summary.unsolved));
+    s.push_str(&format!("    \"unsolvable\": {},\n", summary.unsolvable));
+    s.push_str(&format!("    \"gave_up\": {},\n", summary.gave_up));
+    s.push_str(&format!("    \"invalid\": {},\n", summary.invalid));
+    s.push_str(&format!("    \"solve_rate\": {},\n", summary.solve_rate()));
+    s.push_str(&format!(
+        "    \"moves\": {},\n",
+        distribution(summary.moves)
+    ));
+    s.push_str(&format!(
+        "    \"expanded\": {},\n",
+        distribution(summary.expanded)
+    ));
+    s.push_str(&format!(
+        "    \"elapsed_ms\": {}\n",
+        distribution(summary.elapsed_ms)
+    ));
+    s.push_str("  },\n  \"deals\": [");
+
+    for (i, BatchResult { name, result }) in results.iter().enumerate() {
+        s.push_str(if i == 0 { "\n" } else { ",\n" });
+        s.push_str(&format!(
+            "    {{\"name\": {}, \"status\": \"{}\"",
+            json_string(name),
+            result_status(result)
+        ));
+        if let Ok(solution) = result {
+            s.push_str(&format!(", \"moves\": {}", solution.steps.len()));
+        }
+        if let Some(stats) = result_stats(result) {
+            s.push_str(&format!(
+                ", \"expanded\": {}, \"stored\": {}, \"elapsed_ms\": {}",
+                stats.expanded,
+                stats.stored,
+                stats.elapsed.as_secs_f64() * 1000.
+            ));
+        }
+        s.push('}');
+    }
+
+    s.push_str("\n  ]\n}\n");
+    s
+}
+
+/// Writes the outcome of solving `deal` as a JSON object. The field names are documented in the
+/// README and kept stable.
+pub fn format_solution_json(deal: &Board, result: &Result<Solution, SolveError>) -> String {
+    let mut s = String::from("{\n");
+    s.push_str(&format!("  \"status\": \"{}\",\n", result_status(result)));
+    if let Err(err) = result {
+        s.push_str(&format!(
+            "  \"error\": {},\n",
+            json_string(&strip_colors(&err.to_string()))
+        ));
+    }
+    if let Err(SolveError::Unsolvable { positions, .. }) = result {
+        s.push_str(&format!("  \"positions\": {positions},\n"));
+    }
+    s.push_str(&format!("  \"deal\": {},\n", json_board(deal)));
+
+    if let Ok(solution) = result {
+        s.push_str(&format!(
+            "  \"initial_auto_moves\": {},\n",
+            json_auto_moves(&solution.initial_auto_moves)
+        ));
+        s.push_str("  \"steps\": [");
+        let boards = solution.boards(deal);
+        for (i, (step, board)) in solution.steps.iter().zip(boards.iter()).enumerate() {
+            s.push_str(if i == 0 { "\n" } else { ",\n" });
+            s.push_str(&format!(
+                "    {{\"action\": {}, \"auto_moves\": {}, \"board\": {}}}",
+                json_action(&step.action),
+                json_auto_moves(&step.auto_moves),
+                json_board(board)
+            ));
+        }
+        s.push_str(if solution.steps.is_empty() {
+            "],\n"
+        } else {
+            "\n  ],\n"
+        });
+    }
+
+    let stats = match result_stats(result) {
+        Some(stats) => format!(
+            "{{\"expanded\": {}, \"stored\": {}, \"elapsed_ms\": {}}}",
+            stats.expanded,
+            stats.stored,
+            stats.elapsed.as_secs_f64() * 1000.
+        ),
+        None => "null".to_string(),
+    };
+    s.push_str(&format!("  \"stats\": {stats}\n}}\n"));
+    s
+}
+
+fn json_color(color: Color) -> &'static str {
+    match color {
+        Color::Red => "\"red\"",
+        Color::Green => "\"green\"",
+        Color::Black => "\"black\"",
+    }
+}
+
+fn json_card(card: &Card) -> String {
+    match card {
+        Card::Number(color, number) => format!(
+            "{{\"type\": \"number\", \"color\": {}, \"number\": {number}}}",
+            json_color(*color)
+        ),
+        Card::Dragon(color) => {
+            format!(
+                "{{\"type\": \"dragon\", \"color\": {}}}",
+                json_color(*color)
+            )
+        }
+        Card::Flower => "{\"type\": \"flower\"}".to_string(),
+        Card::CollapsedDragon => "{\"type\": \"collapsed_dragon\"}".to_string(),
+    }
+}
+
+fn json_place(place: &Place) -> String {
+    match place {
+        Place::Tray(index) => format!("{{\"kind\": \"tray\", \"index\": {index}}}"),
+        Place::Slot(index) => format!("{{\"kind\": \"slot\", \"index\": {index}}}"),
+    }
+}
+
+fn json_action(action: &Action) -> String {
+    match action {
+        Action::Pop { src } => format!("{{\"type\": \"pop\", \"src\": {}}}", json_place(src)),
+        Action::Move { src, dest, count } => format!(
+            "{{\"type\": \"move\", \"src\": {}, \"dest\": {}, \"count\": {count}}}",
+            json_place(src),
+            json_place(dest)
+        ),
+        Action::CollapseDragon(color) => format!(
+            "{{\"type\": \"collapse_dragon\", \"color\": {}}}",
+            json_color(*color)
+        ),
+    }
+}
+
+fn json_auto_moves(auto_moves: &[AutoMove]) -> String {
+    let auto_moves: Vec<String> = auto_moves
+        .iter()
+        .map(|auto_move| {
+            format!(
+                "{{\"card\": {}, \"src\": {}}}",
+                json_card(&auto_move.card),
+                json_place(&auto_move.src)
+            )
+        })
+        .collect();
+    format!("[{}]", auto_moves.join(", "))
+}
+
+fn json_board(board: &Board) -> String {
+    let trays: Vec<String> = board
+        .trays
+        .iter()
+        .map(|tray| {
+            let cards: Vec<String> = tray.iter().map(json_card).collect();
+            format!("[{}]", cards.join(", "))
+        })
+        .collect();
+    let slots: Vec<String> = board
+        .slots
+        .iter()
+        .map(|slot| slot.as_ref().map_or("null".to_string(), json_card))
+        .collect();
+    let foundations: Vec<String> = Color::values()
+        .map(|color| {
+            format!(
+                "{}: {}",
+                json_color(color),
+                board.foundations.get(&color).copied().unwrap_or(0)
+            )
+        })
+        .collect();
+    format!(
+        "{{\"trays\": [{}], \"slots\": [{}], \"foundations\": {{{}}}, \"flower\": {}}}",
+        trays.join(", "),
+        slots.join(", "),
+        foundations.join(", "),
+        board.flower
+    )
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+pub fn print_progress(stats: &Stats) {
+    eprintln!(
+        "Iteration {expanded} ({stored} states stored, {elapsed:.2?})",
+        expanded = stats.expanded,
+        stored = stats.stored,
+        elapsed = stats.elapsed,
     );
+}
+
+const PLAY_HELP: &str = "Enter moves in notation, several at once if you like:
+    t3>t5x2  move 2 cards from tray 3 to tray 5
+    t1>s2    move a card from tray 1 to slot 2 (s2>t1 moves it back)
+    t6>f     move a card from tray 6 to the foundations
+    dr       collapse the red dragons (dg, db for green and black)
+Commands:
+    u, undo  take back the last move
+    r, redo  take the undone move again
+    m, moves list the legal moves
+    l, log   list the moves played so far
+    h, help  show this help
+    q, quit  leave the game";
+
+/// Plays `game` interactively, reading moves and commands from `input` line by line until it ends
+/// or a `quit` command, and writing the board drawn in `style` after every change to `output`.
+pub fn play_game(
+    game: &mut Game,
+    input: impl BufRead,
+    mut output: impl Write,
+    style: Style,
+) -> std::io::Result<()> {
+    for auto_move in game.initial_auto_moves() {
+        writeln!(output, "{auto_move}")?;
+    }
+    write!(output, "{}", render_board(&game.board(), style))?;
+    writeln!(output, "Type `help` for the notation.")?;
+    write!(output, "> ")?;
+    output.flush()?;
+
+    for line in input.lines() {
+        let line = line?;
+        let mut changed = false;
+        for token in line.split_whitespace() {
+            match token {
+                "q" | "quit" => return Ok(()),
+                "h" | "help" => writeln!(output, "{PLAY_HELP}")?,
+                "u" | "undo" => match game.undo() {
+                    Some(action) => {
+                        writeln!(output, "Undone: {action}")?;
+                        changed = true;
+                    }
+                    None => writeln!(output, "Nothing to undo")?,
+                },
+                "r" | "redo" => match game.redo() {
+                    Some(action) => {
+                        writeln!(output, "Redone: {action}")?;
+                        changed = true;
+                    }
+                    None => writeln!(output, "Nothing to redo")?,
+                },
+                "m" | "moves" => {
+                    let actions = game.legal_actions();
+                    writeln!(output, "{}", format_notation_line(actions))?;
+                }
+                "l" | "log" => writeln!(output, "{}", format_notation_line(game.actions()))?,
+                _ => {
+                    let action = match token.parse::<Action>() {
+                        Ok(action) => action,
+                        Err(err) => {
+                            writeln!(output, "{err} (type `help` for the notation)")?;
+                            break;
+                        }
+                    };
+                    match game.play(&action) {
+                        Ok(auto_moves) => {
+                            for auto_move in auto_moves {
+                                writeln!(output, "{auto_move}")?;
+                            }
+                            changed = true;
+                        }
+                        Err(reason) => {
+                            writeln!(output, "Illegal move {token}: {reason}")?;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        if changed {
+            write!(output, "{}", render_board(&game.board(), style))?;
+            if game.is_won() {
+                let moves = game.actions().count();
+                writeln!(output, "Cleared in {moves} move(s)!")?;
+            }
+        }
+        write!(output, "> ")?;
+        output.flush()?;
+    }
+
+    writeln!(output)
+}
+
+fn format_notation_line(actions: impl IntoIterator<Item = Action>) -> String {
+    let notations: Vec<_> = actions
+        .into_iter()
+        .map(|action| action.to_notation())
+        .collect();
+    if notations.is_empty() {
+        "(none)".to_string()
+    } else {
+        notations.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::{solve, Step};
+    use crate::fixtures::{load, DEAL, ENDGAME};
+    use std::time::Duration;
+
+    /// One pop clears the board: the green nine, then the red eight and nine by themselves.
+    const ONE_STEP: &str = "r8 g9\nr9\nslots: x x x\nfoundations: r7 g8 b9\nflower: f\n";
+
+    fn one_step_solution() -> Solution {
+        Solution {
+            initial_auto_moves: Vec::new(),
+            steps: vec![Step {
+                action: Action::Pop {
+                    src: Place::Tray(0),
+                },
+                auto_moves: vec![
+                    AutoMove {
+                        card: Card::Number(Color::Red, 8),
+                        src: Place::Tray(0),
+                    },
+                    AutoMove {
+                        card: Card::Number(Color::Red, 9),
+                        src: Place::Tray(1),
+                    },
+                ],
+            }],
+            stats: Stats {
+                expanded: 1,
+                stored: 3,
+                elapsed: Duration::from_millis(2),
+            },
+        }
+    }
+
+    #[test]
+    fn test_load_actions() {
+        let solution = solve(&load(DEAL)).unwrap();
+        let actions: Vec<_> = solution.actions().collect();
+
+        // The colors of a terminal are read through as well.
+        colored::control::set_override(true);
+        let printed = format_solution(&solution);
+        colored::control::unset_override();
+        assert!(printed.contains('\x1b'));
+        assert_eq!(load_actions(&printed), Ok(actions.clone()));
+
+        let notation = format!("# A comment\n{}", format_actions(actions.clone()));
+        assert_eq!(load_actions(&notation), Ok(actions));
+        assert_eq!(
+            load_actions("t1>t2 t3>s1\ndr"),
+            Ok(vec![
+                "t1>t2".parse().unwrap(),
+                "t3>s1".parse().unwrap(),
+                "dr".parse().unwrap(),
+            ])
+        );
+
+        assert_eq!(
+            load_actions("Step  1 Pop from Tray 1"),
+            Err("Invalid step: Step  1 Pop from Tray 1".to_string())
+        );
+        assert!(load_actions("Step  1: Pop from Tray 9").is_err());
+        assert!(load_actions("t1>t9").is_err());
+    }
+
+    #[test]
+    fn test_format_solution_json() {
+        let deal = load(ONE_STEP);
+        let json = format_solution_json(&deal, &Ok(one_step_solution()));
+        let lines: Vec<_> = json.lines().collect();
+        assert_eq!(lines[1], r#"  "status": "solved","#);
+        assert!(lines[2].starts_with(r#"  "deal": {"trays": [[{"type": "number", "color": "red""#));
+        assert_eq!(lines[3], r#"  "initial_auto_moves": [],"#);
+        assert_eq!(lines[4], r#"  "steps": ["#);
+        let step = lines[5];
+        assert!(step.starts_with(
+            r#"    {"action": {"type": "pop", "src": {"kind": "tray", "index": 0}}, "auto_moves": [{"card": {"type": "number", "color": "red", "number": 8}, "src": {"kind": "tray", "index": 0}}, {"card": {"type": "number", "color": "red", "number": 9}, "src": {"kind": "tray", "index": 1}}], "board": "#
+        ));
+        assert!(step.ends_with(
+            r#""board": {"trays": [[], [], [], [], [], [], [], []], "slots": [{"type": "collapsed_dragon"}, {"type": "collapsed_dragon"}, {"type": "collapsed_dragon"}], "foundations": {"red": 9, "green": 9, "black": 9}, "flower": true}}"#
+        ));
+        assert_eq!(lines[6], "  ],");
+        assert_eq!(
+            lines[7],
+            r#"  "stats": {"expanded": 1, "stored": 3, "elapsed_ms": 2}"#
+        );
+        assert_eq!(lines[8], "}");
+
+        let error = SolveError::Unsolvable {
+            positions: 4,
+            stats: Stats {
+                expanded: 4,
+                stored: 4,
+                elapsed: Duration::from_millis(1),
+            },
+        };
+        let json = format_solution_json(&deal, &Err(error));
+        let lines: Vec<_> = json.lines().collect();
+        assert_eq!(lines[1], r#"  "status": "unsolvable","#);
+        assert_eq!(
+            lines[2],
+            r#"  "error": "No solution exists: all 4 reachable positions examined in 4 iterations (1.00ms)","#
+        );
+        assert_eq!(lines[3], r#"  "positions": 4,"#);
+        assert!(lines[4].starts_with(r#"  "deal": "#));
+        assert_eq!(
+            lines[5],
+            r#"  "stats": {"expanded": 4, "stored": 4, "elapsed_ms": 1}"#
+        );
+        assert!(!json.contains("steps"));
+    }
+
+    #[test]
+    fn test_format_batch() {
+        let results = [
+            BatchResult {
+                name: "one, step".to_string(),
+                result: Ok(one_step_solution()),
+            },
+            BatchResult {
+                name: "invalid".to_string(),
+                result: Err(SolveError::InvalidInput("Missing Red card: 1".to_string())),
+            },
+        ];
+
+        assert_eq!(
+            format_batch_csv(&results),
+            "name,status,moves,expanded,stored,elapsed_ms\n\
+             \"one, step\",solved,1,1,3,2.000\n\
+             invalid,invalid,,,,\n"
+        );
+
+        let json = format_batch_json(&results, &BatchSummary::new(&results));
+        let one = r#"{"mean": 1, "p50": 1, "p90": 1, "p99": 1, "max": 1}"#;
+        let two = r#"{"mean": 2, "p50": 2, "p90": 2, "p99": 2, "max": 2}"#;
+        let expected = format!(
+            r#"{{
+  "summary": {{
+    "deals": 2,
+    "solved": 1,
+    "unsolved": 0,
+    "unsolvable": 0,
+    "gave_up": 0,
+    "invalid": 1,
+    "solve_rate": 0.5,
+    "moves": {one},
+    "expanded": {one},
+    "elapsed_ms": {two}
+  }},
+  "deals": [
+    {{"name": "one, step", "status": "solved", "moves": 1, "expanded": 1, "stored": 3, "elapsed_ms": 2}},
+    {{"name": "invalid", "status": "invalid"}}
+  ]
+}}
+"#
+        );
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn test_format_solution_boards() {
+        let deal = load("r7 b9 r8\nb8 r9\nslots: x x x\nfoundations: r6 g9 b7\nflower: f\n");
+        let action: Action = "t1>t3x2".parse().unwrap();
+        let state = Arc::new(State::with_board(&deal)).transit(&action);
+        let solution = Solution {
+            initial_auto_moves: Vec::new(),
+            steps: vec![Step {
+                action,
+                auto_moves: state.auto_moves.clone(),
+            }],
+            stats: one_step_solution().stats,
+        };
+        // The red eight went on to the foundations, so only the black nine is left highlighted.
+        assert_eq!(
+            format_solution_boards_in(&deal, &solution, Style::Ascii),
+            "Found solution of 1 step(s) in 1 iterations (2.00ms)\n\
+             [ (Full) (Full) (Full) ] (Flower) [ (Red 6) (Green 9) (Black 7) ]\n\
+             Tray 1: (Red 7) (Black 9) (Red 8) \n\
+             Tray 2: (Black 8) (Red 9) \n\
+             Tray 3: \n\
+             Tray 4: \n\
+             Tray 5: \n\
+             Tray 6: \n\
+             Tray 7: \n\
+             Tray 8: \n\
+             \n\
+             Step  1: Move 2 cards from Tray 1 to Tray 3\n          \
+             (auto) (Red 7) from Tray 1 to foundation\n          \
+             (auto) (Red 8) from Tray 3 to foundation\n\
+             [ (Full) (Full) (Full) ] (Flower) [ (Red 8) (Green 9) (Black 7) ]\n\
+             Tray 1: \n\
+             Tray 2: (Black 8) (Red 9) \n\
+             Tray 3: *(Black 9)* \n\
+             Tray 4: \n\
+             Tray 5: \n\
+             Tray 6: \n\
+             Tray 7: \n\
+             Tray 8: \n\
+             \n"
+        );
+    }
+
+    #[test]
+    fn test_play_game() {
+        let mut game = Game::new(&load(ENDGAME)).unwrap();
+        let input = "t1>t2 t1>t3\nt1>t3 undo redo\nlog moves\nt2>t1\n";
+        let mut output = Vec::new();
+        play_game(&mut game, input.as_bytes(), &mut output, Style::Ascii).unwrap();
+        let output = String::from_utf8(output).unwrap();
 
-    for (i, action) in actions.iter().enumerate() {
-        println!("Step {i:2}: {action}", i = i + 1);
+        // The rest of a line is dropped after an illegal move, so the first t1>t3 is not played.
+        assert!(output.contains(
+            "> Illegal move t1>t2: cards must be stacked in descending order\n\
+             > (auto) (Red 8) from Tray 1 to foundation\n\
+             Undone: Move 1 card from Tray 1 to Tray 3\n\
+             Redone: Move 1 card from Tray 1 to Tray 3\n"
+        ));
+        assert!(output.contains(
+            "> t1>t3\n\
+             t2>t1 t2>t4 t2>t5 t2>t6 t2>t7 t2>t8 t3>f t3>t1 t3>t4 t3>t5 t3>t6 t3>t7 t3>t8\n"
+        ));
+        assert!(output.ends_with("Cleared in 2 move(s)!\n> \n"));
+        // The board is drawn at the start and after each line that changed it.
+        assert_eq!(output.matches(" t1  t2").count(), 3);
     }
 }
@@ -0,0 +1,37 @@
+mod algo;
+mod batch;
+mod constant;
+#[cfg(test)]
+mod fixtures;
+mod game;
+mod generate;
+mod heuristic;
+mod hint;
+mod io;
+mod render;
+mod rule;
+mod state;
+mod verify;
+
+pub use crate::algo::{
+    solve, solve_with, Budget, Limit, SearchMode, Solution, SolveError, SolveOptions, Stats, Step,
+    Successors,
+};
+pub use crate::batch::{solve_batch, BatchResult, BatchSummary, Distribution};
+pub use crate::game::Game;
+pub use crate::generate::{generate, generate_solvable};
+pub use crate::heuristic::{BlockedCards, Classic, Heuristic, LowerBound, Weighted};
+pub use crate::hint::{hint, Hint, Reason};
+pub use crate::io::{
+    format_actions, format_batch_csv, format_batch_json, format_board, format_solution,
+    format_solution_boards, format_solution_json, load_actions, load_board, load_boards, play_game,
+    print_batch_summary, print_hint, print_progress, print_solution, print_solution_boards,
+    print_solve_error,
+};
+pub use crate::render::{render_board, Style};
+pub use crate::rule::{
+    can_be_stacked, is_legal, validate_game, Action, AutoMove, Card, Color, IllegalMove, Place,
+    DRAGON_COUNT, SLOT_COUNT, TRAY_COUNT,
+};
+pub use crate::state::{Board, State};
+pub use crate::verify::{verify, VerifyError};
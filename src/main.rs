@@ -1,30 +1,387 @@
-mod algo;
-mod constant;
-mod io;
-mod rule;
-mod state;
-
 use std::env::args;
+use std::io::{stdin, stdout};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use sissu::{
+    format_actions, format_batch_csv, format_batch_json, format_board, format_solution_json,
+    generate, generate_solvable, hint, load_actions, load_board, load_boards, play_game,
+    print_batch_summary, print_hint, print_progress, print_solution, print_solution_boards,
+    print_solve_error, render_board, solve_batch, solve_with, verify, BatchSummary, BlockedCards,
+    Board, Classic, Game, Heuristic, LowerBound, SolveOptions, Style, Weighted,
+};
+
+const USAGE: &str = "Usage: sissu [options] <input_file>
+       sissu verify <input_file> <solution_file>
+       sissu generate [--seed <seed>] [--count <count>] [--solvable] [--attempts <count>] [options]
+       sissu batch [--threads <count>] [--csv <file>] [--json <file>] [options] <path>...
+       sissu hint [options] <input_file>
+       sissu show [--ascii] <input_file>
+       sissu play [--seed <seed>] [--ascii] [<input_file>]
+
+Options:
+    --format <text|json|notation>      Output format of the solution (default: text)
+    --boards                           Print the board after each step of the solution
+    --mode <best-first|astar|idastar|prove>
+                                       Search algorithm (default: best-first)
+    --successors <all|pruned>          Moves tried by best-first search (default: all)
+    --heuristic <classic|lower-bound|blocked-cards|<weights>>
+                                       Estimate ordering best-first search (default: classic)
+    --table-size <states>              IDA* transposition table size (default: 65536)
+    --workers <count>                  Threads of a best-first or prove search (default: 1)
+    --max-nodes <count>                Give up after expanding this many states
+    --max-time <seconds>               Give up after this much wall-clock time
+    --max-states <count>               Give up once this many states are stored
+    --progress                         Report search progress on stderr
+
+Generate options:
+    --seed <seed>                      Seed of the first deal (default: from the clock)
+    --count <count>                    Number of deals, separated by `---` lines (default: 1)
+    --solvable                         Skip deals the solver does not clear within its budget
+                                       (default budget: 100000 nodes, unless limited
+                                       by --max-nodes or --max-time)
+    --attempts <count>                 Deals tried for each solvable one (default: 1000)
 
-use crate::algo::solve;
-use crate::io::load_trays_and_slots;
-use crate::rule::validate_game;
+Batch options:
+    --threads <count>                  Deals solved at once (default: number of cores)
+    --csv <file>                       Write the result of every deal as CSV
+    --json <file>                      Write the summary and every result as JSON";
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{message}");
+    eprintln!("{USAGE}");
+    std::process::exit(1);
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: Option<String>) -> T {
+    let value = value.unwrap_or_else(|| usage_error(&format!("Missing value for {flag}")));
+    value
+        .parse()
+        .unwrap_or_else(|_| usage_error(&format!("Invalid value for {flag}: {value}")))
+}
+
+fn read_input(input_file: &str) -> String {
+    std::fs::read_to_string(input_file)
+        .unwrap_or_else(|_| panic!("Failed to read input file: {input_file}"))
+}
+
+fn read_board(input_file: &str) -> Board {
+    load_board(&read_input(input_file)).unwrap_or_else(|err| {
+        println!("Invalid input: {err}");
+        std::process::exit(1);
+    })
+}
 
 fn main() {
-    if args().len() != 2 {
-        eprintln!("Usage: sissu <input_file>");
+    let mut args = args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("verify") => {
+            args.next();
+            run_verify(args)
+        }
+        Some("generate") => {
+            args.next();
+            run_generate(args)
+        }
+        Some("batch") => {
+            args.next();
+            run_batch(args)
+        }
+        Some("play") => {
+            args.next();
+            run_play(args)
+        }
+        Some("hint") => {
+            args.next();
+            run_hint(args)
+        }
+        Some("show") => {
+            args.next();
+            run_show(args)
+        }
+        _ => run_solve(args),
+    }
+}
+
+/// Applies `arg` to `options` if it is a solver option, taking its value from `args`.
+fn parse_solve_option(
+    arg: &str,
+    args: &mut impl Iterator<Item = String>,
+    options: &mut SolveOptions,
+) -> bool {
+    match arg {
+        "--mode" => options.mode = parse_value(arg, args.next()),
+        "--successors" => options.successors = parse_value(arg, args.next()),
+        "--heuristic" => {
+            let value: String = parse_value(arg, args.next());
+            options.heuristic = parse_heuristic(&value)
+                .unwrap_or_else(|_| usage_error(&format!("Invalid value for {arg}: {value}")));
+        }
+        "--table-size" => options.transposition_table_size = parse_value(arg, args.next()),
+        "--workers" => options.workers = parse_value(arg, args.next()),
+        "--max-nodes" => options.budget.max_expanded = Some(parse_value(arg, args.next())),
+        "--max-time" => {
+            let seconds: f64 = parse_value(arg, args.next());
+            options.budget.max_time = Some(
+                Duration::try_from_secs_f64(seconds)
+                    .unwrap_or_else(|_| usage_error(&format!("Invalid value for {arg}"))),
+            );
+        }
+        "--max-states" => options.budget.max_stored = Some(parse_value(arg, args.next())),
+        "--progress" => options.progress = Some(print_progress),
+        _ => return false,
+    }
+    true
+}
+
+/// Parses the name of a heuristic, or the weights of a [`Weighted`] one.
+fn parse_heuristic(value: &str) -> Result<Arc<dyn Heuristic>, String> {
+    Ok(match value {
+        "classic" => Arc::new(Classic),
+        "lower-bound" => Arc::new(LowerBound),
+        "blocked-cards" => Arc::new(BlockedCards),
+        _ => Arc::new(value.parse::<Weighted>()?),
+    })
+}
+
+fn run_solve(mut args: impl Iterator<Item = String>) {
+    let mut options = SolveOptions::default();
+    let mut format = "text".to_string();
+    let mut boards = false;
+    let mut input_file = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = parse_value(&arg, args.next());
+                if !["text", "json", "notation"].contains(&format.as_str()) {
+                    usage_error(&format!("Invalid value for --format: {format}"));
+                }
+            }
+            "--boards" => boards = true,
+            _ if parse_solve_option(&arg, &mut args, &mut options) => {}
+            _ if arg.starts_with("--") => usage_error(&format!("Unknown option: {arg}")),
+            _ if input_file.is_none() => input_file = Some(arg),
+            _ => usage_error(&format!("Unexpected argument: {arg}")),
+        }
+    }
+
+    let input_file = input_file.unwrap_or_else(|| usage_error("Missing input file"));
+    let board = read_board(&input_file);
+
+    let result = solve_with(&board, &options);
+    match (format.as_str(), result) {
+        ("json", result) => print!("{}", format_solution_json(&board, &result)),
+        ("notation", Ok(solution)) => print!("{}", format_actions(solution.actions())),
+        (_, Ok(solution)) if boards => print_solution_boards(&board, &solution),
+        (_, Ok(solution)) => print_solution(&solution),
+        (_, Err(err)) => print_solve_error(&err),
+    }
+}
+
+fn run_hint(mut args: impl Iterator<Item = String>) {
+    let mut options = SolveOptions::default();
+    let mut input_file = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            _ if parse_solve_option(&arg, &mut args, &mut options) => {}
+            _ if arg.starts_with("--") => usage_error(&format!("Unknown option: {arg}")),
+            _ if input_file.is_none() => input_file = Some(arg),
+            _ => usage_error(&format!("Unexpected argument: {arg}")),
+        }
+    }
+
+    let input_file = input_file.unwrap_or_else(|| usage_error("Missing input file"));
+    print_hint(&hint(&read_board(&input_file), &options));
+}
+
+fn run_verify(mut args: impl Iterator<Item = String>) {
+    let (Some(input_file), Some(solution_file), None) = (args.next(), args.next(), args.next())
+    else {
+        usage_error("Expected an input file and a solution file");
+    };
+
+    let board = read_board(&input_file);
+    let actions = load_actions(&read_input(&solution_file)).unwrap_or_else(|err| {
+        println!("Invalid solution: {err}");
         std::process::exit(1);
+    });
+
+    match verify(&board, &actions) {
+        Ok(()) => println!("Valid solution of {} step(s)", actions.len()),
+        Err(err) => {
+            println!("{err}");
+            std::process::exit(1);
+        }
     }
+}
 
-    let input_file = args().nth(1).unwrap();
-    let input = std::fs::read_to_string(&input_file)
-        .expect(format!("Failed to read input file: {input_file}").as_str());
+fn run_show(args: impl Iterator<Item = String>) {
+    let mut style = Style::detect();
+    let mut input_file = None;
+
+    for arg in args {
+        match arg.as_str() {
+            "--ascii" => style = Style::Ascii,
+            _ if arg.starts_with("--") => usage_error(&format!("Unknown option: {arg}")),
+            _ if input_file.is_none() => input_file = Some(arg),
+            _ => usage_error(&format!("Unexpected argument: {arg}")),
+        }
+    }
 
-    let (trays, slots) = load_trays_and_slots(input);
-    if let Err(err) = validate_game(&trays, &slots) {
+    let input_file = input_file.unwrap_or_else(|| usage_error("Missing input file"));
+    print!("{}", render_board(&read_board(&input_file), style));
+}
+
+fn run_play(mut args: impl Iterator<Item = String>) {
+    let mut style = Style::detect();
+    let mut seed = None;
+    let mut input_file = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => seed = Some(parse_value(&arg, args.next())),
+            "--ascii" => style = Style::Ascii,
+            _ if arg.starts_with("--") => usage_error(&format!("Unknown option: {arg}")),
+            _ if input_file.is_none() => input_file = Some(arg),
+            _ => usage_error(&format!("Unexpected argument: {arg}")),
+        }
+    }
+
+    let board = match input_file {
+        Some(input_file) => read_board(&input_file),
+        None => {
+            let seed = seed.unwrap_or_else(clock_seed);
+            println!("Deal of seed {seed}");
+            generate(seed)
+        }
+    };
+    let mut game = Game::new(&board).unwrap_or_else(|err| {
         println!("Invalid game state: {err}");
-        return;
+        std::process::exit(1);
+    });
+
+    play_game(&mut game, stdin().lock(), stdout().lock(), style).expect("Failed to play");
+}
+
+fn clock_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64)
+}
+
+fn run_generate(mut args: impl Iterator<Item = String>) {
+    let mut options = SolveOptions::default();
+    let mut seed = None;
+    let mut count = 1_usize;
+    let mut solvable = false;
+    let mut attempts = 1000;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => seed = Some(parse_value(&arg, args.next())),
+            "--count" => count = parse_value(&arg, args.next()),
+            "--solvable" => solvable = true,
+            "--attempts" => attempts = parse_value(&arg, args.next()),
+            _ if parse_solve_option(&arg, &mut args, &mut options) => {}
+            _ => usage_error(&format!("Unexpected argument: {arg}")),
+        }
     }
 
-    solve(&trays, &slots);
+    if options.budget.max_expanded.is_none() && options.budget.max_time.is_none() {
+        options.budget.max_expanded = Some(100000);
+    }
+    let mut seed = seed.unwrap_or_else(clock_seed);
+
+    for i in 0..count {
+        let board = if solvable {
+            let Some((solvable_seed, board)) = generate_solvable(seed, &options, attempts) else {
+                eprintln!("No deal solved within the budget among {attempts} from seed {seed}");
+                std::process::exit(1);
+            };
+            seed = solvable_seed;
+            board
+        } else {
+            generate(seed)
+        };
+
+        if i > 0 {
+            println!("---");
+        }
+        println!("# seed {seed}");
+        print!("{}", format_board(&board));
+        seed = seed.wrapping_add(1);
+    }
+}
+
+fn run_batch(mut args: impl Iterator<Item = String>) {
+    let mut options = SolveOptions::default();
+    let mut threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+    let mut csv_file = None;
+    let mut json_file = None;
+    let mut paths = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--threads" => threads = parse_value(&arg, args.next()),
+            "--csv" => csv_file = Some(parse_value::<String>(&arg, args.next())),
+            "--json" => json_file = Some(parse_value::<String>(&arg, args.next())),
+            _ if parse_solve_option(&arg, &mut args, &mut options) => {}
+            _ if arg.starts_with("--") => usage_error(&format!("Unknown option: {arg}")),
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        usage_error("Missing input file or directory");
+    }
+
+    let mut deals = Vec::new();
+    for path in paths {
+        let mut files = Vec::new();
+        if std::path::Path::new(&path).is_dir() {
+            let entries = std::fs::read_dir(&path)
+                .unwrap_or_else(|_| panic!("Failed to read input directory: {path}"));
+            for entry in entries.flatten() {
+                if entry.path().is_file() {
+                    files.push(entry.path().to_string_lossy().into_owned());
+                }
+            }
+            files.sort();
+        } else {
+            files.push(path);
+        }
+
+        for file in files {
+            let boards = load_boards(&read_input(&file)).unwrap_or_else(|err| {
+                println!("Invalid input in {file}: {err}");
+                std::process::exit(1);
+            });
+            let single = boards.len() == 1;
+            for (i, board) in boards.into_iter().enumerate() {
+                let name = if single {
+                    file.clone()
+                } else {
+                    format!("{file}:{}", i + 1)
+                };
+                deals.push((name, board));
+            }
+        }
+    }
+
+    let results = solve_batch(&deals, &options, threads);
+    let summary = BatchSummary::new(&results);
+    print_batch_summary(&summary);
+
+    let write_output = |file: &str, content: String| {
+        std::fs::write(file, content)
+            .unwrap_or_else(|_| panic!("Failed to write output file: {file}"));
+    };
+    if let Some(file) = csv_file {
+        write_output(&file, format_batch_csv(&results));
+    }
+    if let Some(file) = json_file {
+        write_output(&file, format_batch_json(&results, &summary));
+    }
 }
@@ -0,0 +1,147 @@
+use crate::io::{card_token, strip_colors};
+use crate::rule::{Card, Color, TRAY_COUNT};
+use crate::state::Board;
+
+use colored::Colorize;
+
+/// Width of a column, in characters.
+const CELL_WIDTH: usize = 4;
+
+/// How [`render_board`] draws the cards.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Style {
+    /// Plain ASCII, with cards written as in the input format (`r3`, `gd`, `f`, `x`).
+    Ascii,
+    /// Suit glyphs in the color of their suit.
+    Color,
+}
+
+impl Style {
+    /// [`Style::Color`] when the rest of the output is colored as well (stdout is a terminal and
+    /// `NO_COLOR` is not set), [`Style::Ascii`] otherwise.
+    pub fn detect() -> Self {
+        if colored::control::SHOULD_COLORIZE.should_colorize() {
+            Style::Color
+        } else {
+            Style::Ascii
+        }
+    }
+}
+
+/// Draws the board the way the game lays it out: the slots, the flower slot and the foundations
+/// across the top, and the trays below as columns, bottom card first.
+pub fn render_board(board: &Board, style: Style) -> String {
+    let mut s = String::new();
+
+    // The top row spans the tray columns: slots on the left, foundations on the right, and the
+    // flower slot in between.
+    let mut labels = vec![String::new(); TRAY_COUNT];
+    let mut top = vec![String::new(); TRAY_COUNT];
+    for (i, slot) in board.slots.iter().enumerate() {
+        labels[i] = format!("s{}", i + 1);
+        top[i] = render_cell(*slot, style);
+    }
+    let flower = TRAY_COUNT - Color::values().count() - 1;
+    labels[flower] = "fl".to_string();
+    top[flower] = render_cell(board.flower.then_some(Card::Flower), style);
+    for (i, color) in Color::values().enumerate() {
+        let card = match board.foundations.get(&color) {
+            Some(&number) if number > 0 => Some(Card::Number(color, number)),
+            _ => None,
+        };
+        top[flower + 1 + i] = render_cell(card, style);
+    }
+
+    push_row(&mut s, &labels);
+    push_row(&mut s, &top);
+    s.push('\n');
+
+    let labels: Vec<_> = (1..=TRAY_COUNT).map(|i| format!("t{i}")).collect();
+    push_row(&mut s, &labels);
+    let height = board.trays.iter().map(Vec::len).max().unwrap_or(0);
+    for row in 0..height {
+        let cells: Vec<_> = board
+            .trays
+            .iter()
+            .map(|tray| match tray.get(row) {
+                Some(&card) => render_cell(Some(card), style),
+                None => String::new(),
+            })
+            .collect();
+        push_row(&mut s, &cells);
+    }
+
+    s
+}
+
+/// Appends a row of cells, each padded to [`CELL_WIDTH`], without trailing spaces.
+fn push_row(s: &mut String, cells: &[String]) {
+    let mut row = String::new();
+    for cell in cells {
+        let width = strip_colors(cell).chars().count();
+        row.push_str(&" ".repeat(CELL_WIDTH.saturating_sub(width + 1)));
+        row.push_str(cell);
+        row.push(' ');
+    }
+    s.push_str(row.trim_end());
+    s.push('\n');
+}
+
+fn render_cell(card: Option<Card>, style: Style) -> String {
+    match style {
+        Style::Ascii => card.as_ref().map_or("-".to_string(), card_token),
+        Style::Color => match card {
+            None => "··".dimmed().to_string(),
+            Some(Card::Number(color, number)) => paint(color, &format!("{number}{}", glyph(color))),
+            Some(Card::Dragon(color)) => paint(color, &format!("D{}", glyph(color))),
+            Some(Card::Flower) => "✿✿".magenta().to_string(),
+            Some(Card::CollapsedDragon) => "▒▒".dimmed().to_string(),
+        },
+    }
+}
+
+fn glyph(color: Color) -> char {
+    match color {
+        Color::Red => '♦',
+        Color::Green => '♣',
+        Color::Black => '♠',
+    }
+}
+
+fn paint(color: Color, text: &str) -> String {
+    match color {
+        Color::Red => text.red(),
+        Color::Green => text.green(),
+        // Plain bold rather than black, which dark terminals would hide.
+        Color::Black => text.bold(),
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::load_board;
+
+    #[test]
+    fn test_render_board() {
+        let board = load_board("r1 gd f\nb9\n\n\n\n\n\ng2 g3\nslots: rd - x\nfoundations: b3 g1\n")
+            .unwrap();
+        assert_eq!(
+            render_board(&board, Style::Ascii),
+            " s1  s2  s3      fl\
+           \n rd   -   x       -   -  g1  b3\
+           \n\
+           \n t1  t2  t3  t4  t5  t6  t7  t8\
+           \n r1  b9                      g2\
+           \n gd                          g3\
+           \n  f\n"
+        );
+
+        let colored = render_board(&board, Style::Color);
+        assert_eq!(
+            strip_colors(&colored).lines().nth(4),
+            Some(" 1♦  9♠                      2♣")
+        );
+    }
+}
@@ -1,18 +1,32 @@
-pub(crate) use crate::constant::{DRAGON_COUNT, SLOT_COUNT, TRAY_COUNT};
+pub use crate::constant::{DRAGON_COUNT, SLOT_COUNT, TRAY_COUNT};
+use crate::state::{Board, State};
+
 use std::collections::HashMap;
 use std::str::FromStr;
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-pub(crate) enum Color {
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Color {
     Red,
     Green,
     Black,
 }
 
+/// Number of colors, the length of the arrays indexed by `color as usize`.
+pub(crate) const COLOR_COUNT: usize = 3;
+
 impl Color {
-    pub(crate) fn values() -> impl Iterator<Item = Color> {
+    pub fn values() -> impl Iterator<Item = Color> {
         [Color::Red, Color::Green, Color::Black].iter().copied()
     }
+
+    /// The letter of the color in cards and moves, as parsed by [`Color::from_str`].
+    pub fn to_notation(self) -> char {
+        match self {
+            Color::Red => 'r',
+            Color::Green => 'g',
+            Color::Black => 'b',
+        }
+    }
 }
 
 impl FromStr for Color {
@@ -28,8 +42,8 @@ impl FromStr for Color {
     }
 }
 
-#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
-pub(crate) enum Card {
+#[derive(Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, Debug)]
+pub enum Card {
     CollapsedDragon,
     Dragon(Color),
     Flower,
@@ -45,9 +59,9 @@ impl FromStr for Card {
         // Check for special cards
         match s {
             "f" | "ff" => return Ok(Card::Flower),
-            "rd" | "rr" => return Ok(Card::Dragon(Color::Red)),
-            "gd" | "gg" => return Ok(Card::Dragon(Color::Green)),
-            "bd" | "bb" => return Ok(Card::Dragon(Color::Black)),
+            "dr" | "rd" | "rr" => return Ok(Card::Dragon(Color::Red)),
+            "dg" | "gd" | "gg" => return Ok(Card::Dragon(Color::Green)),
+            "db" | "bd" | "bb" => return Ok(Card::Dragon(Color::Black)),
             _ => {}
         }
 
@@ -62,7 +76,7 @@ impl FromStr for Card {
                     if let (Ok(color), Ok(number)) =
                         (Color::from_str(color_part), number_part.parse::<i8>())
                     {
-                        if number >= 1 && number <= 9 {
+                        if (1..=9).contains(&number) {
                             return Ok(Card::Number(color, number));
                         }
                     }
@@ -74,14 +88,41 @@ impl FromStr for Card {
     }
 }
 
-#[derive(Clone, Copy)]
-pub(crate) enum Place {
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Place {
     Tray(usize),
     Slot(usize),
 }
 
-#[derive(Clone, Copy)]
-pub(crate) enum Action {
+impl FromStr for Place {
+    type Err = String;
+
+    /// Parses `t<n>` or `s<n>`, with trays and slots counted from 1.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (place, count): (fn(usize) -> Place, usize) = match s.get(..1) {
+            Some("t") => (Place::Tray, TRAY_COUNT),
+            Some("s") => (Place::Slot, SLOT_COUNT),
+            _ => return Err(format!("Invalid place: {s}")),
+        };
+        match s[1..].parse::<usize>() {
+            Ok(index) if (1..=count).contains(&index) => Ok(place(index - 1)),
+            _ => Err(format!("Invalid place: {s}")),
+        }
+    }
+}
+
+impl Place {
+    /// The compact notation of the place, as parsed by [`Place::from_str`].
+    pub fn to_notation(&self) -> String {
+        match self {
+            Place::Tray(index) => format!("t{}", index + 1),
+            Place::Slot(index) => format!("s{}", index + 1),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
     Pop {
         src: Place,
     },
@@ -93,7 +134,67 @@ pub(crate) enum Action {
     CollapseDragon(Color),
 }
 
-pub(crate) fn can_be_stacked(src: Card, dest: Card) -> bool {
+impl FromStr for Action {
+    type Err = String;
+
+    /// Parses the compact notation: `t3>t5x2` moves two cards from tray 3 to tray 5, `t1>s2` and
+    /// `s1>t4` move a single card, `t6>f` pops a card to the foundations and `dr` collapses the
+    /// red dragons.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Some(color) = s.strip_prefix('d') {
+            return Ok(Action::CollapseDragon(Color::from_str(color)?));
+        }
+
+        let Some((src, dest)) = s.split_once('>') else {
+            return Err(format!("Invalid action: {s}"));
+        };
+        let src = src.parse::<Place>()?;
+        if dest == "f" {
+            return Ok(Action::Pop { src });
+        }
+
+        let (dest, count) = match dest.split_once('x') {
+            Some((dest, count)) => match count.parse::<usize>() {
+                Ok(count) if count > 0 => (dest, count),
+                _ => return Err(format!("Invalid card count: {count}")),
+            },
+            None => (dest, 1),
+        };
+        Ok(Action::Move {
+            src,
+            dest: dest.parse::<Place>()?,
+            count,
+        })
+    }
+}
+
+impl Action {
+    /// The compact notation of the action, as parsed by [`Action::from_str`].
+    pub fn to_notation(&self) -> String {
+        match self {
+            Action::Pop { src } => format!("{}>f", src.to_notation()),
+            Action::Move { src, dest, count } => {
+                let mut notation = format!("{}>{}", src.to_notation(), dest.to_notation());
+                if *count > 1 {
+                    notation.push_str(&format!("x{count}"));
+                }
+                notation
+            }
+            Action::CollapseDragon(color) => format!("d{}", color.to_notation()),
+        }
+    }
+}
+
+/// A card the game moves to the foundations (or the flower slot) by itself.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct AutoMove {
+    pub card: Card,
+    pub src: Place,
+}
+
+pub fn can_be_stacked(src: Card, dest: Card) -> bool {
     match (src, dest) {
         (Card::Number(color_src, number_src), Card::Number(color_dest, number_dest)) => {
             color_src != color_dest && number_src + 1 == number_dest
@@ -102,73 +203,220 @@ pub(crate) fn can_be_stacked(src: Card, dest: Card) -> bool {
     }
 }
 
-pub(crate) fn validate_game(
-    trays: &[Vec<Card>; TRAY_COUNT],
-    slots: &[Option<Card>; SLOT_COUNT],
-) -> Result<(), String> {
+pub fn validate_game(board: &Board) -> Result<(), String> {
     let mut bucket = HashMap::<Card, usize>::new();
-    for tray in trays.iter() {
+    for tray in board.trays.iter() {
         for card in tray.iter() {
             *bucket.entry(*card).or_insert(0) += 1;
         }
     }
-    for slot in slots.iter() {
-        if let Some(card) = slot {
-            *bucket.entry(*card).or_insert(0) += 1;
-        }
+    for card in board.slots.iter().flatten() {
+        *bucket.entry(*card).or_insert(0) += 1;
     }
 
     let get = |card: Card| -> usize { bucket.get(&card).copied().unwrap_or(0) };
 
+    let mut collapsed_colors = 0;
     for color in Color::values() {
         let dragon_count = get(Card::Dragon(color));
-        if dragon_count > DRAGON_COUNT {
-            return Err(format!("Too many {} dragons: {}", color, dragon_count).into());
+        if dragon_count == 0 {
+            collapsed_colors += 1;
+        } else if dragon_count > DRAGON_COUNT {
+            return Err(format!("Too many {} dragons: {}", color, dragon_count));
+        } else if dragon_count < DRAGON_COUNT {
+            return Err(format!(
+                "Missing {} dragons: {}",
+                color,
+                DRAGON_COUNT - dragon_count
+            ));
         }
 
-        {
-            let mut missing = false;
-            for number in 9..=1 {
-                let card_count = get(Card::Number(color, number));
+        let foundation = board.foundations.get(&color).copied().unwrap_or(0);
+        if !(0..=9).contains(&foundation) {
+            return Err(format!("Invalid {} foundation: {}", color, foundation));
+        }
+        for number in 1..=9 {
+            let card_count = get(Card::Number(color, number));
+            if number <= foundation {
+                if card_count > 0 {
+                    return Err(format!(
+                        "{} card {} is already on the foundation",
+                        color, number
+                    ));
+                }
+            } else {
                 match card_count {
-                    0 => missing = true,
-                    1 => {
-                        if missing {
-                            return Err(format!("Missing {} card: {}", color, number + 1).into());
-                        }
-                    }
-                    _ => return Err(format!("Too many {} cards: {}", color, number).into()),
+                    0 => return Err(format!("Missing {} card: {}", color, number)),
+                    1 => {}
+                    _ => return Err(format!("Too many {} cards: {}", color, number)),
                 }
             }
         }
     }
 
-    Ok(())
-}
+    let collapsed_slots = get(Card::CollapsedDragon);
+    if collapsed_slots != collapsed_colors {
+        return Err(format!(
+            "{} collapsed dragon slot(s) for {} collapsed dragon color(s)",
+            collapsed_slots, collapsed_colors
+        ));
+    }
 
-pub(crate) trait Pile {
-    fn remove_to_foundations(&mut self);
+    match (get(Card::Flower), board.flower) {
+        (0, false) => return Err("Missing flower".to_string()),
+        (1, true) => return Err("Flower is already placed".to_string()),
+        (0, true) | (1, false) => {}
+        _ => return Err("Too many flowers".to_string()),
+    }
 
-    fn top_card(&self) -> Option<Card>;
+    Ok(())
 }
 
-impl Pile for Vec<Card> {
-    fn remove_to_foundations(&mut self) {
-        self.pop();
-    }
-
-    fn top_card(&self) -> Option<Card> {
-        self.last().copied()
-    }
+/// Why an action cannot be taken in a position.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IllegalMove {
+    /// The tray or slot does not exist.
+    NoSuchPlace(Place),
+    /// There is no card to take from the source.
+    EmptySource,
+    /// The move is of zero cards.
+    NoCards,
+    /// The source holds fewer cards than moved, or more than one card goes to or from a slot.
+    TooManyCards,
+    /// The moved cards are not stacked in alternating colors and descending numbers.
+    NotARun,
+    /// The moved card has the same color as the card it would be stacked on.
+    WrongColor,
+    /// The moved card is not one lower than the card it would be stacked on.
+    WrongNumber,
+    /// Dragons, the flower and collapsed dragons never take part in a stack.
+    NotStackable,
+    /// The source and the destination are the same.
+    SamePlace,
+    /// The destination slot is not empty.
+    SlotOccupied,
+    /// Collapsed dragons stay in their slot for the rest of the game.
+    CollapsedSlot,
+    /// The card is not the next one on its foundation.
+    NotNextOnFoundation,
+    /// Fewer than four dragons of the color are exposed.
+    DragonsNotExposed,
+    /// No slot is empty or holds one of the dragons to collapse.
+    DragonSlotUnavailable,
 }
 
-impl Pile for Option<Card> {
-    fn remove_to_foundations(&mut self) {
-        self.take();
-    }
+/// Checks whether `action` can be taken in `state`, without relying on move generation.
+pub fn is_legal(state: &State, action: &Action) -> Result<(), IllegalMove> {
+    let check_place = |place: Place| match place {
+        Place::Tray(index) if index < TRAY_COUNT => Ok(()),
+        Place::Slot(index) if index < SLOT_COUNT => Ok(()),
+        _ => Err(IllegalMove::NoSuchPlace(place)),
+    };
+
+    match *action {
+        Action::Pop { src } => {
+            check_place(src)?;
+            let card = match src {
+                Place::Tray(index) => state.trays[index].last().copied(),
+                Place::Slot(index) => state.slots[index],
+            };
+            match card {
+                None => Err(IllegalMove::EmptySource),
+                Some(Card::Number(color, number))
+                    if state.foundations[color as usize] + 1 == number =>
+                {
+                    Ok(())
+                }
+                Some(Card::CollapsedDragon) => Err(IllegalMove::CollapsedSlot),
+                Some(_) => Err(IllegalMove::NotNextOnFoundation),
+            }
+        }
+        Action::Move { src, dest, count } => {
+            check_place(src)?;
+            check_place(dest)?;
+            if src == dest {
+                return Err(IllegalMove::SamePlace);
+            }
+            if count == 0 {
+                return Err(IllegalMove::NoCards);
+            }
 
-    fn top_card(&self) -> Option<Card> {
-        *self
+            let cards = match src {
+                Place::Tray(index) => {
+                    let tray = &state.trays[index];
+                    if tray.is_empty() {
+                        return Err(IllegalMove::EmptySource);
+                    }
+                    if count > tray.len() {
+                        return Err(IllegalMove::TooManyCards);
+                    }
+                    &tray[tray.len() - count..]
+                }
+                Place::Slot(index) => match &state.slots[index] {
+                    None => return Err(IllegalMove::EmptySource),
+                    Some(Card::CollapsedDragon) => return Err(IllegalMove::CollapsedSlot),
+                    Some(_) if count > 1 => return Err(IllegalMove::TooManyCards),
+                    Some(card) => std::slice::from_ref(card),
+                },
+            };
+
+            match dest {
+                Place::Tray(index) => {
+                    if cards
+                        .windows(2)
+                        .any(|pair| !can_be_stacked(pair[1], pair[0]))
+                    {
+                        return Err(IllegalMove::NotARun);
+                    }
+                    match (cards[0], state.trays[index].last()) {
+                        (_, None) => Ok(()),
+                        (
+                            Card::Number(color_src, number_src),
+                            Some(&Card::Number(color_dest, number_dest)),
+                        ) => {
+                            if color_src == color_dest {
+                                Err(IllegalMove::WrongColor)
+                            } else if number_src + 1 != number_dest {
+                                Err(IllegalMove::WrongNumber)
+                            } else {
+                                Ok(())
+                            }
+                        }
+                        _ => Err(IllegalMove::NotStackable),
+                    }
+                }
+                Place::Slot(index) => {
+                    if count > 1 {
+                        Err(IllegalMove::TooManyCards)
+                    } else if state.slots[index].is_some() {
+                        Err(IllegalMove::SlotOccupied)
+                    } else {
+                        Ok(())
+                    }
+                }
+            }
+        }
+        Action::CollapseDragon(color) => {
+            let dragon = Card::Dragon(color);
+            let exposed = state
+                .trays
+                .iter()
+                .filter_map(|tray| tray.last())
+                .chain(state.slots.iter().flatten())
+                .filter(|&&card| card == dragon)
+                .count();
+            if exposed < DRAGON_COUNT {
+                return Err(IllegalMove::DragonsNotExposed);
+            }
+            if !state
+                .slots
+                .iter()
+                .any(|&slot| slot.is_none() || slot == Some(dragon))
+            {
+                return Err(IllegalMove::DragonSlotUnavailable);
+            }
+            Ok(())
+        }
     }
 }
 
@@ -205,6 +453,151 @@ mod tests {
         assert!("cd".parse::<Card>().is_err());
     }
 
+    #[test]
+    fn test_validate_game() {
+        let mut board = Board::default();
+        board.trays[0] = vec![Card::Number(Color::Red, 9)];
+        board.slots = [Some(Card::CollapsedDragon); SLOT_COUNT];
+        board.foundations = HashMap::from([(Color::Red, 8), (Color::Green, 9), (Color::Black, 9)]);
+        board.flower = true;
+        assert!(validate_game(&board).is_ok());
+
+        board.flower = false;
+        assert!(validate_game(&board).is_err());
+        board.flower = true;
+
+        board.foundations.insert(Color::Red, 9);
+        assert!(validate_game(&board).is_err());
+        board.foundations.insert(Color::Red, 7);
+        assert!(validate_game(&board).is_err());
+        board.foundations.insert(Color::Red, 8);
+
+        board.slots[0] = None;
+        assert!(validate_game(&board).is_err());
+        board.slots[0] = Some(Card::CollapsedDragon);
+
+        // Foundations only go from empty to nine.
+        board.trays[0] = (1..=9)
+            .map(|number| Card::Number(Color::Red, number))
+            .collect();
+        board.foundations.insert(Color::Red, -1);
+        assert!(validate_game(&board).is_err());
+        board.trays[0].clear();
+        board.foundations.insert(Color::Red, 12);
+        assert!(validate_game(&board).is_err());
+    }
+
+    #[test]
+    fn test_is_legal() {
+        let board = crate::fixtures::load(crate::fixtures::DEAL);
+        let state = State::with_board(&board);
+
+        for action in state
+            .valid_actions()
+            .iter()
+            .chain(&state.valid_slot_actions())
+        {
+            assert_eq!(is_legal(&state, action), Ok(()), "{action}");
+        }
+
+        let illegal_moves = [
+            (Place::Tray(4), Place::Tray(0), 1, IllegalMove::NotStackable),
+            (Place::Tray(4), Place::Tray(0), 2, IllegalMove::NotARun),
+            (Place::Tray(2), Place::Tray(3), 1, IllegalMove::WrongColor),
+            (Place::Tray(7), Place::Tray(3), 1, IllegalMove::WrongNumber),
+            (Place::Tray(6), Place::Tray(2), 1, IllegalMove::NotStackable),
+            (Place::Tray(0), Place::Tray(0), 1, IllegalMove::SamePlace),
+            (Place::Tray(0), Place::Slot(0), 2, IllegalMove::TooManyCards),
+            (Place::Slot(0), Place::Tray(0), 1, IllegalMove::EmptySource),
+            (
+                Place::Tray(0),
+                Place::Tray(8),
+                1,
+                IllegalMove::NoSuchPlace(Place::Tray(8)),
+            ),
+        ];
+        for (src, dest, count, reason) in illegal_moves {
+            let action = Action::Move { src, dest, count };
+            assert_eq!(is_legal(&state, &action), Err(reason), "{action}");
+        }
+
+        assert_eq!(
+            is_legal(&state, &Action::CollapseDragon(Color::Red)),
+            Err(IllegalMove::DragonsNotExposed)
+        );
+        assert_eq!(
+            is_legal(
+                &state,
+                &Action::Pop {
+                    src: Place::Tray(0)
+                }
+            ),
+            Err(IllegalMove::NotNextOnFoundation)
+        );
+    }
+
+    #[test]
+    fn test_action_notation() {
+        let actions = [
+            (
+                "t3>t5x2",
+                Action::Move {
+                    src: Place::Tray(2),
+                    dest: Place::Tray(4),
+                    count: 2,
+                },
+            ),
+            (
+                "t1>s2",
+                Action::Move {
+                    src: Place::Tray(0),
+                    dest: Place::Slot(1),
+                    count: 1,
+                },
+            ),
+            (
+                "s1>t4",
+                Action::Move {
+                    src: Place::Slot(0),
+                    dest: Place::Tray(3),
+                    count: 1,
+                },
+            ),
+            (
+                "t6>f",
+                Action::Pop {
+                    src: Place::Tray(5),
+                },
+            ),
+            (
+                "s3>f",
+                Action::Pop {
+                    src: Place::Slot(2),
+                },
+            ),
+            ("dr", Action::CollapseDragon(Color::Red)),
+            ("db", Action::CollapseDragon(Color::Black)),
+        ];
+        for (notation, action) in actions {
+            assert_eq!(notation.parse::<Action>().unwrap(), action);
+            assert_eq!(action.to_notation(), notation);
+        }
+
+        assert_eq!("t1>t2x1".parse::<Action>().unwrap().to_notation(), "t1>t2");
+        for color in Color::values() {
+            assert_eq!(color.to_notation().to_string().parse::<Color>(), Ok(color));
+        }
+
+        // Error cases
+        assert!("t0>t1".parse::<Action>().is_err()); // Trays are counted from 1
+        assert!("t9>t1".parse::<Action>().is_err());
+        assert!("s4>t1".parse::<Action>().is_err());
+        assert!("t1>t2x0".parse::<Action>().is_err());
+        assert!("t1t2".parse::<Action>().is_err());
+        assert!("f>t1".parse::<Action>().is_err());
+        assert!("dx".parse::<Action>().is_err());
+    }
+
     #[test]
     fn test_color_from_str() {
         assert_eq!("r".parse::<Color>().unwrap(), Color::Red);
@@ -2,31 +2,34 @@ use crate::rule::*;
 
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
-use std::rc::Rc;
-
-type Priority = f64;
+use std::ops::{Index, Range};
+use std::sync::Arc;
+
+/// A position as dealt or loaded from input, without any search bookkeeping.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Board {
+    pub trays: [Vec<Card>; TRAY_COUNT],
+    pub slots: [Option<Card>; SLOT_COUNT],
+    /// Highest number already played on the foundation of each color. Missing colors have none.
+    pub foundations: HashMap<Color, i8>,
+    /// Whether the flower is already placed.
+    pub flower: bool,
+}
 
-pub(crate) struct State {
-    lowest_each_suit: HashMap<Color, i8>,
-    pub(crate) trays: [Vec<Card>; TRAY_COUNT],
+/// A position reached while playing or searching a deal, as given to a
+/// [`Heuristic`](crate::Heuristic).
+pub struct State {
+    /// Highest number played on the foundation of each color, zero if none, indexed by color.
+    pub(crate) foundations: [i8; COLOR_COUNT],
+    pub(crate) flower: bool,
+    pub(crate) trays: Trays,
     pub(crate) slots: [Option<Card>; SLOT_COUNT],
     pub(crate) action: Option<Action>,
-    pub(crate) prev_state: Option<Rc<State>>,
-    step: usize,
+    /// Cards the game moved by itself right after `action`.
+    pub(crate) auto_moves: Vec<AutoMove>,
+    pub(crate) prev_state: Option<Arc<State>>,
+    pub(crate) step: usize,
     pub(crate) card_count: usize,
-    priority: Priority,
-}
-
-impl PartialOrd for State {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.priority.partial_cmp(&other.priority)
-    }
-}
-
-impl Ord for State {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.priority.partial_cmp(&other.priority).unwrap()
-    }
 }
 
 impl PartialEq for State {
@@ -44,97 +47,379 @@ impl Hash for State {
     }
 }
 
+/// The cards of every tray in a single buffer, tray after tray, each bottom card first.
+///
+/// Indexing gives the cards of a tray as a slice. The buffer holds a whole deck, so moving cards
+/// never allocates.
+#[derive(Clone)]
+pub(crate) struct Trays {
+    cards: [Card; CARD_COUNT],
+    /// Where each tray ends in `cards`. A tray starts where the one before it ends.
+    ends: [u8; TRAY_COUNT],
+}
+
+impl Default for Trays {
+    fn default() -> Self {
+        Self {
+            cards: [Card::Flower; CARD_COUNT],
+            ends: [0; TRAY_COUNT],
+        }
+    }
+}
+
+impl Trays {
+    fn new(trays: &[Vec<Card>; TRAY_COUNT]) -> Self {
+        let mut result = Self::default();
+        for (i, tray) in trays.iter().enumerate() {
+            for &card in tray {
+                result.push(i, card);
+            }
+        }
+        result
+    }
+
+    fn to_vecs(&self) -> [Vec<Card>; TRAY_COUNT] {
+        std::array::from_fn(|i| self[i].to_vec())
+    }
+
+    fn range(&self, index: usize) -> Range<usize> {
+        let start = match index {
+            0 => 0,
+            _ => self.ends[index - 1] as usize,
+        };
+        start..self.ends[index] as usize
+    }
+
+    /// Number of cards in all the trays.
+    fn len(&self) -> usize {
+        self.ends[TRAY_COUNT - 1] as usize
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &[Card]> {
+        (0..TRAY_COUNT).map(|i| &self[i])
+    }
+
+    pub(crate) fn push(&mut self, index: usize, card: Card) {
+        let (end, len) = (self.ends[index] as usize, self.len());
+        self.cards.copy_within(end..len, end + 1);
+        self.cards[end] = card;
+        for end in &mut self.ends[index..] {
+            *end += 1;
+        }
+    }
+
+    pub(crate) fn pop(&mut self, index: usize) -> Option<Card> {
+        let range = self.range(index);
+        if range.is_empty() {
+            return None;
+        }
+        let (card, len) = (self.cards[range.end - 1], self.len());
+        self.cards.copy_within(range.end..len, range.end - 1);
+        for end in &mut self.ends[index..] {
+            *end -= 1;
+        }
+        Some(card)
+    }
+
+    /// Moves the top `count` cards of tray `src` onto tray `dest`, keeping their order.
+    pub(crate) fn move_cards(&mut self, src: usize, dest: usize, count: usize) {
+        let (src_end, dest_end) = (self.ends[src] as usize, self.ends[dest] as usize);
+        // The cards between the run and where it goes only shift over by the run's length.
+        if src < dest {
+            self.cards[src_end - count..dest_end].rotate_left(count);
+            for end in &mut self.ends[src..dest] {
+                *end -= count as u8;
+            }
+        } else {
+            self.cards[dest_end..src_end].rotate_right(count);
+            for end in &mut self.ends[dest..src] {
+                *end += count as u8;
+            }
+        }
+    }
+}
+
+impl Index<usize> for Trays {
+    type Output = [Card];
+
+    fn index(&self, index: usize) -> &[Card] {
+        &self.cards[self.range(index)]
+    }
+}
+
+impl PartialEq for Trays {
+    fn eq(&self, other: &Self) -> bool {
+        self.ends == other.ends && self.cards[..self.len()] == other.cards[..other.len()]
+    }
+}
+
+impl Eq for Trays {}
+
+impl Hash for Trays {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ends.hash(state);
+        self.cards[..self.len()].hash(state);
+    }
+}
+
+/// Bits of a card code in a [`Packed`] position.
+const CARD_BITS: usize = 6;
+/// Words of a [`Packed`] position: every card, an end marker after each tray and the slots.
+const PACKED_WORDS: usize =
+    ((CARD_COUNT + TRAY_COUNT + SLOT_COUNT) * CARD_BITS).div_ceil(u64::BITS as usize);
+/// Cards in a deck: nine numbers and the dragons of each color, and the flower.
+const CARD_COUNT: usize = 3 * (9 + DRAGON_COUNT) + 1;
+
+/// A position packed into a few words, as kept in the visited sets and open lists of the
+/// searches instead of a full [`State`].
+///
+/// It holds the cards of each tray, bottom first and followed by an end marker, then the cards
+/// of the slots. The foundations and the flower are implied by the cards missing from the board.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct Packed([u64; PACKED_WORDS]);
+
+impl Packed {
+    /// Packs the position with its real layout, so that [`Packed::unpack`] gives it back.
+    pub(crate) fn new(state: &State) -> Self {
+        Self::pack(state.trays.iter(), &state.slots)
+    }
+
+    /// Packs the position up to the order of its trays and of its slots.
+    ///
+    /// Which tray or slot holds a pile makes no difference to solving a position, so positions
+    /// that only differ by it pack the same.
+    pub(crate) fn canonical(state: &State) -> Self {
+        let mut trays: [&[Card]; TRAY_COUNT] = std::array::from_fn(|i| &state.trays[i]);
+        trays.sort_unstable();
+        let mut slots = state.slots;
+        slots.sort_unstable();
+        Self::pack(trays.into_iter(), &slots)
+    }
+
+    fn pack<'a>(
+        trays: impl Iterator<Item = &'a [Card]>,
+        slots: &[Option<Card>; SLOT_COUNT],
+    ) -> Self {
+        let mut packed = Self([0; PACKED_WORDS]);
+        let mut position = 0;
+        let mut push = |code: u64| {
+            let (word, bit) = (position / 64, position % 64);
+            packed.0[word] |= code << bit;
+            if bit + CARD_BITS > 64 {
+                packed.0[word + 1] |= code >> (64 - bit);
+            }
+            position += CARD_BITS;
+        };
+
+        for tray in trays {
+            for &card in tray {
+                push(card_code(Some(card)));
+            }
+            push(card_code(None));
+        }
+        for &slot in slots {
+            push(card_code(slot));
+        }
+        packed
+    }
+
+    /// Rebuilds the position, which was reached in `step` actions, with its real layout.
+    pub(crate) fn unpack(&self, step: usize) -> State {
+        let mut position = 0;
+        let mut pop = || {
+            let (word, bit) = (position / 64, position % 64);
+            let mut code = self.0[word] >> bit;
+            if bit + CARD_BITS > 64 {
+                code |= self.0[word + 1] << (64 - bit);
+            }
+            position += CARD_BITS;
+            code_card(code & ((1 << CARD_BITS) - 1))
+        };
+
+        let mut trays = Trays::default();
+        for i in 0..TRAY_COUNT {
+            while let Some(card) = pop() {
+                trays.push(i, card);
+            }
+        }
+        let slots = std::array::from_fn(|_| pop());
+
+        let mut foundations = [9; COLOR_COUNT];
+        let mut flower = true;
+        for &card in trays.iter().flatten().chain(slots.iter().flatten()) {
+            match card {
+                Card::Number(color, number) => {
+                    let foundation = &mut foundations[color as usize];
+                    *foundation = (*foundation).min(number - 1);
+                }
+                Card::Flower => flower = false,
+                _ => {}
+            }
+        }
+
+        let mut state = State::with_parts(foundations, flower, trays, slots);
+        state.step = step;
+        state
+    }
+}
+
+fn card_code(card: Option<Card>) -> u64 {
+    match card {
+        None => 0,
+        Some(Card::CollapsedDragon) => 1,
+        Some(Card::Flower) => 2,
+        Some(Card::Dragon(color)) => 3 + color as u64,
+        Some(Card::Number(color, number)) => 6 + color as u64 * 9 + (number - 1) as u64,
+    }
+}
+
+fn code_card(code: u64) -> Option<Card> {
+    let color = |index: u64| Color::values().nth(index as usize).unwrap();
+    match code {
+        0 => None,
+        1 => Some(Card::CollapsedDragon),
+        2 => Some(Card::Flower),
+        3..=5 => Some(Card::Dragon(color(code - 3))),
+        _ => Some(Card::Number(
+            color((code - 6) / 9),
+            ((code - 6) % 9) as i8 + 1,
+        )),
+    }
+}
+
 impl State {
+    /// The cards of each tray, bottom card first.
+    pub fn trays(&self) -> impl Iterator<Item = &[Card]> {
+        self.trays.iter()
+    }
+
+    pub fn slots(&self) -> &[Option<Card>; SLOT_COUNT] {
+        &self.slots
+    }
+
+    /// Highest number played on the foundation of `color`, zero if none.
+    pub fn foundation(&self, color: Color) -> i8 {
+        self.foundations[color as usize]
+    }
+
+    /// Number of actions taken to reach the position.
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Number of cards left to clear, collapsed dragons aside.
+    pub fn card_count(&self) -> usize {
+        self.card_count
+    }
+
     #[allow(unused)]
     pub(crate) fn new() -> Self {
-        const EMPTY_VEC_CARD: Vec<Card> = vec![];
         Self {
-            lowest_each_suit: HashMap::new(),
-            trays: [EMPTY_VEC_CARD; TRAY_COUNT],
+            foundations: [0; COLOR_COUNT],
+            flower: false,
+            trays: Trays::default(),
             slots: [None; SLOT_COUNT],
             action: None,
+            auto_moves: Vec::new(),
             prev_state: None,
             step: 0,
             card_count: 0,
-            priority: 0.,
         }
     }
 
-    pub(crate) fn with_trays_and_slots(
-        trays: &[Vec<Card>; TRAY_COUNT],
-        slots: &[Option<Card>; SLOT_COUNT],
+    pub fn to_board(&self) -> Board {
+        Board {
+            trays: self.trays.to_vecs(),
+            slots: self.slots,
+            foundations: Color::values()
+                .map(|color| (color, self.foundations[color as usize]))
+                .filter(|&(_, number)| number > 0)
+                .collect(),
+            flower: self.flower,
+        }
+    }
+
+    /// The position on `board`, once the game moved every card it would by itself. The board
+    /// should pass [`validate_game`]: trays holding more than a deck panic.
+    pub fn with_board(board: &Board) -> Self {
+        let foundations = Color::values()
+            .map(|color| board.foundations.get(&color).copied().unwrap_or(0))
+            .collect::<Vec<_>>();
+        Self::with_parts(
+            foundations.try_into().unwrap(),
+            board.flower,
+            Trays::new(&board.trays),
+            board.slots,
+        )
+    }
+
+    fn with_parts(
+        foundations: [i8; COLOR_COUNT],
+        flower: bool,
+        trays: Trays,
+        slots: [Option<Card>; SLOT_COUNT],
     ) -> Self {
         let mut state = Self {
-            lowest_each_suit: HashMap::new(),
-            trays: trays.to_owned(),
-            slots: slots.to_owned(),
+            foundations,
+            flower,
+            trays,
+            slots,
             action: None,
+            auto_moves: Vec::new(),
             prev_state: None,
             step: 0,
             card_count: 0,
-            priority: 0.,
         };
-        state.auto_remove_cards(); // lowest_per_suit is updated here
+        state.auto_remove_cards();
         state.card_count = state.calc_card_count();
-        state.priority = state.calc_priority();
         state
     }
 
-    pub(crate) fn transit(self: &Rc<Self>, action: &Action) -> Rc<State> {
+    /// Takes `action` and returns the position it leads to, or why [`is_legal`] refuses it.
+    pub fn try_transit(self: &Arc<Self>, action: &Action) -> Result<Arc<State>, IllegalMove> {
+        is_legal(self, action)?;
+        Ok(self.transit(action))
+    }
+
+    pub(crate) fn transit(self: &Arc<Self>, action: &Action) -> Arc<State> {
         let mut state = State {
-            lowest_each_suit: HashMap::new(),
+            foundations: self.foundations,
+            flower: self.flower,
             trays: self.trays.clone(),
             slots: self.slots,
             step: self.step + 1,
             action: Some(*action),
+            auto_moves: Vec::new(),
             prev_state: Some(self.clone()),
             card_count: 0,
-            priority: 0.,
         };
 
         match *action {
-            Action::Pop { src } => match src {
-                Place::Tray(tray) => {
-                    state.trays[tray].pop();
-                }
-                Place::Slot(_) => {
-                    panic!("Impossible to pop from slot.");
+            Action::Pop { src } => {
+                let card = match src {
+                    Place::Tray(tray) => state.trays.pop(tray),
+                    Place::Slot(slot) => state.slots[slot].take(),
+                };
+                if let Some(Card::Number(color, number)) = card {
+                    state.foundations[color as usize] = number;
                 }
-            },
+            }
             Action::Move {
                 src: from,
                 dest: to,
                 count,
-            } => {
-                let mut cards_to_be_moved = Vec::with_capacity(count);
-
-                match from {
-                    Place::Tray(tray) => {
-                        for _ in 0..count {
-                            cards_to_be_moved.push(state.trays[tray].pop().unwrap());
-                        }
-                    }
-                    Place::Slot(slot) => {
-                        cards_to_be_moved.push(state.slots[slot].unwrap());
-                        state.slots[slot] = None;
-                    }
-                }
-
-                match to {
-                    Place::Tray(tray) => {
-                        for card in cards_to_be_moved.into_iter().rev() {
-                            state.trays[tray].push(card);
-                        }
-                    }
-                    Place::Slot(slot) => {
-                        state.slots[slot] = Some(cards_to_be_moved[0]);
-                    }
+            } => match (from, to) {
+                (Place::Tray(from), Place::Tray(to)) => state.trays.move_cards(from, to, count),
+                (Place::Tray(from), Place::Slot(to)) => state.slots[to] = state.trays.pop(from),
+                (Place::Slot(from), Place::Tray(to)) => {
+                    state.trays.push(to, state.slots[from].take().unwrap())
                 }
-            }
+                (Place::Slot(from), Place::Slot(to)) => state.slots[to] = state.slots[from].take(),
+            },
             Action::CollapseDragon(color) => {
-                for tray in state.trays.iter_mut() {
-                    if !tray.is_empty() && *tray.last().unwrap() == Card::Dragon(color) {
-                        tray.pop();
+                for i in 0..TRAY_COUNT {
+                    if state.trays[i].last() == Some(&Card::Dragon(color)) {
+                        state.trays.pop(i);
                     }
                 }
 
@@ -155,11 +440,10 @@ impl State {
             }
         }
 
-        state.auto_remove_cards(); // lowest_per_suit is updated here
+        state.auto_remove_cards();
         state.card_count = state.calc_card_count();
-        state.priority = state.calc_priority();
 
-        Rc::new(state)
+        Arc::new(state)
     }
 
     fn calc_card_count(&self) -> usize {
@@ -179,97 +463,83 @@ impl State {
         count
     }
 
-    fn calc_priority(&self) -> Priority {
-        let mut priority_of_cards: Priority = 0.;
+    /// A lower bound on the number of actions still needed to clear the board.
+    ///
+    /// Every dragon color still on the board needs its own collapse. A tray where a number card
+    /// sits above a lower card of the same color needs at least one move out of it, and every
+    /// dragon above another dragon of its color needs a move of its own. Moves take cards from a
+    /// single tray and dragons never travel in a run, so none of these can share an action.
+    pub fn lower_bound(&self) -> usize {
+        let mut bound = 0;
+        let mut uncollapsed = [false; COLOR_COUNT];
 
         for tray in self.trays.iter() {
-            if tray.is_empty() {
-                continue;
+            let mut lowest_below = [i8::MAX; COLOR_COUNT];
+            let mut dragons_below = [0; COLOR_COUNT];
+            let mut blocked = false;
+
+            for &card in tray.iter() {
+                match card {
+                    Card::Number(color, number) => {
+                        let lowest = &mut lowest_below[color as usize];
+                        if *lowest < number {
+                            blocked = true;
+                        } else {
+                            *lowest = number;
+                        }
+                    }
+                    Card::Dragon(color) => {
+                        uncollapsed[color as usize] = true;
+                        let count = &mut dragons_below[color as usize];
+                        if *count > 0 {
+                            bound += 1;
+                        }
+                        *count += 1;
+                    }
+                    _ => {}
+                }
             }
 
-            let mut cards_stacked_cur_tray = 0;
-
-            for i in ((tray.len() - 1)..0).rev() {
-                if can_be_stacked(tray[i], tray[i - 1]) {
-                    cards_stacked_cur_tray += 1;
-                }
+            if blocked {
+                bound += 1;
             }
+        }
 
-            if cards_stacked_cur_tray == tray.len() - 1 {
-                if let Card::Number(_, 9) = tray[0] {
-                    priority_of_cards += cards_stacked_cur_tray as f64 * 1.2;
-                } else {
-                    priority_of_cards += cards_stacked_cur_tray as f64 * 1.1;
-                }
-            } else {
-                priority_of_cards += cards_stacked_cur_tray as f64;
+        for card in self.slots.iter().flatten() {
+            if let Card::Dragon(color) = card {
+                uncollapsed[*color as usize] = true;
             }
         }
 
-        self.card_count as f64 + self.step as f64 * 0.1 - priority_of_cards
+        bound
+            + uncollapsed
+                .iter()
+                .filter(|&&uncollapsed| uncollapsed)
+                .count()
     }
 
+    /// Moves the flower and every number card the game would play by itself to the foundations,
+    /// until there are none left.
     fn auto_remove_cards(&mut self) {
         loop {
             let mut call_again = false;
-            self.lowest_each_suit =
-                HashMap::from([(Color::Red, 10), (Color::Green, 10), (Color::Black, 10)]);
-
-            for tray in self.trays.iter_mut() {
-                if tray.is_empty() {
-                    continue;
-                }
-
-                if let Card::Flower = tray.last().unwrap() {
-                    tray.pop();
-                    call_again = true;
-                } else if let Card::Number(_, 1) = tray.last().unwrap() {
-                    tray.pop();
-                    call_again = true;
-                }
-
-                for &card in tray.iter() {
-                    if let Card::Number(color, number) = card {
-                        if number < self.lowest_each_suit[&color] {
-                            self.lowest_each_suit.insert(color, number);
-                        }
-                    }
-                }
-            }
 
-            for &card in self.slots.iter() {
-                if let Some(Card::Number(color, number)) = card {
-                    if number < self.lowest_each_suit[&color] {
-                        self.lowest_each_suit.insert(color, number);
+            for i in 0..TRAY_COUNT {
+                if let Some(&card) = self.trays[i].last() {
+                    if self.auto_remove(card, Place::Tray(i)) {
+                        self.trays.pop(i);
+                        call_again = true;
                     }
                 }
             }
 
-            let mut try_remove_points_gt_one = |pile: &mut dyn Pile| {
-                if let Some(Card::Number(color, number)) = pile.top_card() {
-                    if number > 2 {
-                        if number <= self.lowest_each_suit[&Color::Red]
-                            && number <= self.lowest_each_suit[&Color::Green]
-                            && number <= self.lowest_each_suit[&Color::Black]
-                        {
-                            pile.remove_to_foundations();
-                            call_again = true;
-                        }
-                    } else if number == 2 && number == self.lowest_each_suit[&color] {
-                        pile.remove_to_foundations();
+            for i in 0..SLOT_COUNT {
+                if let Some(card) = self.slots[i] {
+                    if self.auto_remove(card, Place::Slot(i)) {
+                        self.slots[i] = None;
                         call_again = true;
                     }
                 }
-            };
-
-            for tray in self.trays.iter_mut() {
-                if !tray.is_empty() {
-                    try_remove_points_gt_one(tray);
-                }
-            }
-
-            for card in self.slots.iter_mut() {
-                try_remove_points_gt_one(card);
             }
 
             if !call_again {
@@ -278,13 +548,32 @@ impl State {
         }
     }
 
+    /// Plays `card` to the foundations (or the flower slot) if the game would do it by itself,
+    /// and returns whether it did. Taking the card off `src` is left to the caller.
+    fn auto_remove(&mut self, card: Card, src: Place) -> bool {
+        match card {
+            Card::Flower => self.flower = true,
+            Card::Number(color, number) => {
+                let is_next = self.foundations[color as usize] + 1 == number;
+                let is_safe = number <= 2
+                    || self
+                        .foundations
+                        .iter()
+                        .all(|&foundation| foundation + 1 >= number);
+                if !is_next || !is_safe {
+                    return false;
+                }
+                self.foundations[color as usize] = number;
+            }
+            _ => return false,
+        }
+        self.auto_moves.push(AutoMove { card, src });
+        true
+    }
+
     pub(crate) fn valid_actions(&self) -> Vec<Action> {
         let mut actions = Vec::new();
-        let mut exposed_dragon_count = HashMap::from([
-            (Color::Red, 0),
-            (Color::Green, 0),
-            (Color::Black, 0),
-        ]);
+        let mut exposed_dragon_count = [0; COLOR_COUNT];
 
         for (i, tray) in self.trays.iter().enumerate() {
             if tray.is_empty() {
@@ -292,9 +581,9 @@ impl State {
             }
 
             if let &Card::Dragon(color) = tray.last().unwrap() {
-                *exposed_dragon_count.get_mut(&color).unwrap() += 1;
+                exposed_dragon_count[color as usize] += 1;
             } else if let &Card::Number(color, number) = tray.last().unwrap() {
-                if self.lowest_each_suit[&color] == number {
+                if self.foundations[color as usize] + 1 == number {
                     actions.push(Action::Pop {
                         src: Place::Tray(i),
                     });
@@ -333,11 +622,7 @@ impl State {
         }
 
         let mut has_empty_slot = false;
-        let mut has_empty_slot_for_specicific_dragon = HashMap::from([
-            (Color::Red, false),
-            (Color::Green, false),
-            (Color::Black, false),
-        ]);
+        let mut has_empty_slot_for_specicific_dragon = [false; COLOR_COUNT];
 
         for (i, &slot) in self.slots.iter().enumerate() {
             match slot {
@@ -349,8 +634,14 @@ impl State {
                 }
                 Some(card) => {
                     if let Card::Dragon(color) = card {
-                        *exposed_dragon_count.get_mut(&color).unwrap() += 1;
-                        has_empty_slot_for_specicific_dragon.insert(color, true);
+                        exposed_dragon_count[color as usize] += 1;
+                        has_empty_slot_for_specicific_dragon[color as usize] = true;
+                    } else if let Card::Number(color, number) = card {
+                        if self.foundations[color as usize] + 1 == number {
+                            actions.push(Action::Pop {
+                                src: Place::Slot(i),
+                            });
+                        }
                     }
                     for (j, tray) in self.trays.iter().enumerate() {
                         if tray.is_empty() || can_be_stacked(card, *tray.last().unwrap()) {
@@ -366,8 +657,8 @@ impl State {
         }
 
         for color in Color::values() {
-            if exposed_dragon_count[&color] == DRAGON_COUNT
-                && (has_empty_slot || has_empty_slot_for_specicific_dragon[&color])
+            if exposed_dragon_count[color as usize] == DRAGON_COUNT
+                && (has_empty_slot || has_empty_slot_for_specicific_dragon[color as usize])
             {
                 actions.push(Action::CollapseDragon(color));
             }
@@ -398,3 +689,57 @@ impl State {
         actions
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{load, ENDGAME};
+
+    #[test]
+    fn test_trays() {
+        let card = |s: &str| s.parse::<Card>().unwrap();
+        let mut vecs: [Vec<Card>; TRAY_COUNT] = Default::default();
+        vecs[1] = vec![card("r5"), card("g4"), card("r3")];
+        vecs[4] = vec![card("b6")];
+        let mut trays = Trays::new(&vecs);
+
+        trays.move_cards(1, 4, 2);
+        assert_eq!(trays[1], [card("r5")]);
+        assert_eq!(trays[4], [card("b6"), card("g4"), card("r3")]);
+        trays.move_cards(4, 0, 3);
+        assert_eq!(trays[0], [card("b6"), card("g4"), card("r3")]);
+        assert!(trays[4].is_empty());
+
+        assert_eq!(trays.pop(0), Some(card("r3")));
+        assert_eq!(trays.pop(4), None);
+        trays.push(7, card("gd"));
+        assert_eq!(trays[1], [card("r5")]);
+        assert_eq!(trays[7], [card("gd")]);
+        assert_eq!(trays.len(), 4);
+    }
+
+    #[test]
+    fn test_packed() {
+        let board = load(ENDGAME);
+        let state = State::with_board(&board);
+        let unpacked = Packed::new(&state).unpack(3);
+        assert!(unpacked == state);
+        assert_eq!(unpacked.foundations, state.foundations);
+        assert_eq!(unpacked.step, 3);
+        assert!(unpacked.flower);
+
+        let mut swapped = board.clone();
+        swapped.trays.swap(0, 5);
+        swapped.slots.swap(0, 2);
+        let swapped = State::with_board(&swapped);
+        assert_ne!(Packed::new(&state), Packed::new(&swapped));
+        assert_eq!(Packed::canonical(&state), Packed::canonical(&swapped));
+
+        let moved = Arc::new(state).transit(&Action::Move {
+            src: Place::Tray(0),
+            dest: Place::Tray(2),
+            count: 1,
+        });
+        assert_ne!(Packed::canonical(&swapped), Packed::canonical(&moved));
+    }
+}
@@ -0,0 +1,74 @@
+use crate::rule::{validate_game, Action, IllegalMove};
+use crate::state::{Board, State};
+
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+pub enum VerifyError {
+    /// The board failed [`validate_game`].
+    InvalidInput(String),
+    /// The action at `step` (counted from 1) cannot be taken in the position it is applied to.
+    IllegalAction {
+        step: usize,
+        action: Action,
+        reason: IllegalMove,
+    },
+    /// Every action was legal, but cards are left on the board.
+    NotCleared { cards_left: usize },
+}
+
+/// Replays `actions` from `board` and checks that each of them is legal and that they clear the
+/// board.
+pub fn verify(board: &Board, actions: &[Action]) -> Result<(), VerifyError> {
+    validate_game(board).map_err(VerifyError::InvalidInput)?;
+
+    let mut state = Arc::new(State::with_board(board));
+    for (i, action) in actions.iter().enumerate() {
+        state = state
+            .try_transit(action)
+            .map_err(|reason| VerifyError::IllegalAction {
+                step: i + 1,
+                action: *action,
+                reason,
+            })?;
+    }
+
+    match state.card_count {
+        0 => Ok(()),
+        cards_left => Err(VerifyError::NotCleared { cards_left }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::solve;
+    use crate::fixtures::{load, ENDGAME};
+    use crate::rule::Place;
+
+    #[test]
+    fn test_verify() {
+        let board = load(ENDGAME);
+        let actions: Vec<_> = solve(&board).unwrap().actions().collect();
+        assert!(verify(&board, &actions).is_ok());
+
+        assert!(matches!(
+            verify(&board, &actions[..1]),
+            Err(VerifyError::NotCleared { .. })
+        ));
+
+        let illegal = Action::Move {
+            src: Place::Tray(0),
+            dest: Place::Tray(1),
+            count: 1,
+        };
+        assert!(matches!(
+            verify(&board, &[illegal]),
+            Err(VerifyError::IllegalAction {
+                step: 1,
+                reason: IllegalMove::WrongNumber,
+                ..
+            })
+        ));
+    }
+}